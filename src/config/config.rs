@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::generator::convention::ExportConvention;
+use crate::generator::generator::SortOrder;
+use crate::generator::templates::Template;
+use crate::loader::loader::Filter;
+use crate::parser::ast::Scope;
+
+/// Name of the config file searched for from `--path` upwards.
+pub const CONFIG_FILE: &str = "ts-scss-modules.toml";
+
+/// Key of the config in a `package.json`.
+pub const PACKAGE_JSON_KEY: &str = "tsScssModules";
+
+/// Settings from a config file. Every field is optional, command line flags
+/// win over the file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct Config {
+    pub log_level: Option<String>,
+    pub threads: Option<usize>,
+    pub mode: Option<Scope>,
+    pub export_convention: Option<ExportConvention>,
+    pub sort: Option<SortOrder>,
+    pub template: Option<String>, // Built-in name or path relative to the config file
+    pub named_exports: Option<bool>,
+    pub keep_default_export: Option<bool>,
+    pub banner: Option<String>,
+    pub source_hash: Option<bool>,
+    pub out_dir: Option<PathBuf>, // Relative to the config file
+    pub debounce: Option<u64>,
+    pub modules_pattern: Option<String>, // Globs relative to --path
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub no_ignore: Option<bool>,
+}
+
+impl Config {
+    /// Reads a `ts-scss-modules.toml` or the `tsScssModules` key of a
+    /// `package.json`. `None` when a `package.json` has no such key.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+        };
+        let config = if path.file_name().is_some_and(|name| name == "package.json") {
+            let package = serde_json::from_str::<serde_json::Value>(&content)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let Some(value) = package.get(PACKAGE_JSON_KEY) else {
+                return Ok(None);
+            };
+            Config::deserialize(value)
+                .map_err(|e| format!("{}: invalid \"{}\": {}", path.display(), PACKAGE_JSON_KEY, e))?
+        } else {
+            toml::from_str::<Config>(&content).map_err(|e| format!("{}: {}", path.display(), e))?
+        };
+        config.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let directory = path.parent().unwrap_or(Path::new("."));
+        Ok(Some(config.resolve_paths(directory)))
+    }
+
+    /// The first config found in `start` or one of its parents, with its
+    /// path. In each directory a `ts-scss-modules.toml` wins over a
+    /// `package.json`.
+    pub fn find(start: &Path) -> Result<Option<(PathBuf, Self)>, String> {
+        for directory in start.ancestors() {
+            for name in [CONFIG_FILE, "package.json"] {
+                let path = directory.join(name);
+                if !path.is_file() {
+                    continue;
+                }
+                if let Some(config) = Self::load(&path)? {
+                    return Ok(Some((path, config)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.threads == Some(0) {
+            return Err("threads must be at least 1".to_string());
+        }
+        if self.out_dir.as_ref().is_some_and(|out_dir| out_dir.as_os_str().is_empty()) {
+            return Err("outDir must not be empty".to_string());
+        }
+        let filter = Filter::new(
+            self.include.as_deref().unwrap_or_default(),
+            self.exclude.as_deref().unwrap_or_default(),
+        )?;
+        if let Some(modules_pattern) = &self.modules_pattern {
+            filter.with_modules_pattern(modules_pattern)?;
+        }
+        Ok(())
+    }
+
+    /// Makes the paths of the config relative to the config file's directory
+    /// instead of the working directory.
+    fn resolve_paths(mut self, directory: &Path) -> Self {
+        if let Some(template) = &self.template {
+            if Template::builtin(template).is_none() {
+                self.template = Some(directory.join(template).display().to_string());
+            }
+        }
+        self.out_dir = self.out_dir.map(|out_dir| directory.join(out_dir));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(
+            &path,
+            "threads = 8\nexportConvention = \"camelCaseOnly\"\nsort = \"source\"\ntemplate = \"templates/styles.hbs\"\noutDir = \"types\"\nnamedExports = true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap(),
+            Some(Config {
+                threads: Some(8),
+                export_convention: Some(ExportConvention::CamelCaseOnly),
+                sort: Some(SortOrder::Source),
+                template: Some(dir.path().join("templates/styles.hbs").display().to_string()),
+                out_dir: Some(dir.path().join("types")),
+                named_exports: Some(true),
+                ..Config::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+
+        fs::write(&path, "theads = 8\n").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert!(error.contains("unknown field `theads`"), "{error}");
+        assert!(error.contains("line 1"), "{error}");
+
+        fs::write(&path, "mode = \"pure\"\n").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert!(error.contains("unknown variant `pure`, expected `local` or `global`"), "{error}");
+
+        fs::write(&path, "threads = 0\n").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert!(error.ends_with("threads must be at least 1"), "{error}");
+
+        fs::write(&path, "outDir = \"\"\n").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert!(error.ends_with("outDir must not be empty"), "{error}");
+
+        fs::write(&path, "exclude = [\"src/[legacy\"]\n").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert!(error.contains("Invalid pattern src/[legacy"), "{error}");
+
+        let path = dir.path().join("package.json");
+        fs::write(&path, "{ \"tsScssModules\": { \"sort\": 1 } }").unwrap();
+        let error = Config::load(&path).unwrap_err();
+        assert!(error.contains("invalid \"tsScssModules\""), "{error}");
+    }
+
+    #[test]
+    fn test_find_walks_up_to_package_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("packages/app/src");
+        fs::create_dir_all(&nested).unwrap();
+        // Without the key the search goes on
+        fs::write(dir.path().join("packages/app/package.json"), "{ \"name\": \"app\" }").unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            "{ \"name\": \"root\", \"tsScssModules\": { \"template\": \"interface\", \"mode\": \"global\" } }",
+        )
+        .unwrap();
+
+        let (path, config) = Config::find(&nested).unwrap().unwrap();
+        assert_eq!(path, dir.path().join("package.json"));
+        assert_eq!(config.template.as_deref(), Some("interface"));
+        assert_eq!(config.mode, Some(Scope::Global));
+
+        fs::write(dir.path().join("packages").join(CONFIG_FILE), "threads = 2\n").unwrap();
+        let (path, config) = Config::find(&nested).unwrap().unwrap();
+        assert_eq!(path, dir.path().join("packages").join(CONFIG_FILE));
+        assert_eq!(config.threads, Some(2));
+    }
+}
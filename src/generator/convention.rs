@@ -0,0 +1,177 @@
+/// How class names and `:export` keys are turned into keys of the styles
+/// object, matching css-loader's `exportLocalsConvention`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportConvention {
+    #[default]
+    #[value(name = "asIs")]
+    AsIs, // Keep names as they are
+    #[value(name = "camelCase")]
+    CamelCase, // Keep names and add a camelCase version
+    #[value(name = "camelCaseOnly")]
+    CamelCaseOnly, // Only the camelCase version
+    #[value(name = "dashes")]
+    Dashes, // Keep names and add a version with only dashes camelCased
+    #[value(name = "dashesOnly")]
+    DashesOnly, // Only the version with dashes camelCased
+}
+
+impl ExportConvention {
+    /// Keys exported for `name`, the original first when it is kept.
+    pub fn apply(&self, name: &str) -> Vec<String> {
+        let (converted, keep_original) = match self {
+            ExportConvention::AsIs => return vec![name.to_string()],
+            ExportConvention::CamelCase => (camel_case(name), true),
+            ExportConvention::CamelCaseOnly => (camel_case(name), false),
+            ExportConvention::Dashes => (dashes_camel_case(name), true),
+            ExportConvention::DashesOnly => (dashes_camel_case(name), false),
+        };
+        if keep_original && converted != name {
+            vec![name.to_string(), converted]
+        } else {
+            vec![converted]
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '_' | '.' | '-' | ' ')
+}
+
+/// camelCase like the `camelcase` package css-loader uses: separators are
+/// dropped, the character after them and after digits is uppercased and
+/// existing camel humps are kept.
+pub fn camel_case(name: &str) -> String {
+    let name = name.trim();
+    if name.chars().count() <= 1 {
+        return name.to_lowercase();
+    }
+    let name = if name.to_lowercase() != name {
+        preserve_camel_case(name)
+    } else {
+        name.to_string()
+    };
+    let lowered = name.trim_start_matches(is_separator).to_lowercase();
+
+    let mut result = String::new();
+    let mut chars = lowered.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_separator(c) {
+            while chars.next_if(|&next| is_separator(next)).is_some() {}
+            // A run of separators uppercases the next letter, digits are
+            // handled below
+            if let Some(next) = chars.next_if(|&next| next.is_alphabetic() || next == '_') {
+                result.extend(next.to_uppercase());
+            }
+        } else if c.is_numeric() {
+            result.push(c);
+            while let Some(digit) = chars.next_if(|next| next.is_numeric()) {
+                result.push(digit);
+            }
+            if let Some(next) = chars.next_if(|&next| next.is_alphabetic() || next == '_') {
+                result.extend(next.to_uppercase());
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Inserts a '-' at each camel hump so the humps survive lowercasing, like
+/// `fooBar` to `foo-Bar` and `FOOBar` to `FOO-Bar`.
+fn preserve_camel_case(name: &str) -> String {
+    let mut chars = name.chars().collect::<Vec<_>>();
+    let mut last_lower = false;
+    let mut last_upper = false;
+    let mut last_last_upper = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if last_lower && c.is_uppercase() {
+            chars.insert(i, '-');
+            last_lower = false;
+            last_last_upper = last_upper;
+            last_upper = true;
+            i += 1;
+        } else if last_upper && last_last_upper && c.is_lowercase() {
+            chars.insert(i - 1, '-');
+            last_last_upper = last_upper;
+            last_upper = false;
+            last_lower = true;
+        } else {
+            last_lower = c.is_lowercase();
+            last_last_upper = last_upper;
+            last_upper = c.is_uppercase();
+        }
+        i += 1;
+    }
+    chars.into_iter().collect()
+}
+
+/// Only camelCases dashes, like css-loader's `dashesCamelCase`: a run of
+/// dashes followed by a word character is replaced by that character
+/// uppercased.
+pub fn dashes_camel_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '-' {
+            result.push(c);
+            continue;
+        }
+        let mut dashes = String::from(c);
+        while let Some(dash) = chars.next_if(|&next| next == '-') {
+            dashes.push(dash);
+        }
+        match chars.next_if(|next| next.is_ascii_alphanumeric() || *next == '_') {
+            Some(next) => result.extend(next.to_uppercase()),
+            None => result.push_str(&dashes),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("my-button"), "myButton");
+        assert_eq!(camel_case("a--b"), "aB");
+        assert_eq!(camel_case("_foo"), "foo");
+        assert_eq!(camel_case("__foo__bar__"), "fooBar");
+        assert_eq!(camel_case("2col"), "2Col");
+        assert_eq!(camel_case("btn-2x"), "btn2X");
+        assert_eq!(camel_case("is---active"), "isActive");
+        assert_eq!(camel_case("trailing-"), "trailing");
+        assert_eq!(camel_case("block__element--modifier"), "blockElementModifier");
+        assert_eq!(camel_case("fooBar"), "fooBar");
+        assert_eq!(camel_case("Foo-Bar"), "fooBar");
+        assert_eq!(camel_case("HTMLParser"), "htmlParser");
+        assert_eq!(camel_case("A"), "a");
+    }
+
+    #[test]
+    fn test_dashes_camel_case() {
+        assert_eq!(dashes_camel_case("my-button"), "myButton");
+        assert_eq!(dashes_camel_case("a--b"), "aB");
+        assert_eq!(dashes_camel_case("_foo"), "_foo");
+        assert_eq!(dashes_camel_case("2col"), "2col");
+        assert_eq!(dashes_camel_case("foo_bar-baz"), "foo_barBaz");
+        assert_eq!(dashes_camel_case("is---active"), "isActive");
+        assert_eq!(dashes_camel_case("trailing-"), "trailing-");
+        assert_eq!(dashes_camel_case("-leading"), "Leading");
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(ExportConvention::AsIs.apply("a-b"), vec!["a-b"]);
+        assert_eq!(ExportConvention::CamelCase.apply("a-b"), vec!["a-b", "aB"]);
+        assert_eq!(ExportConvention::CamelCase.apply("ab"), vec!["ab"]);
+        assert_eq!(ExportConvention::CamelCaseOnly.apply("a_b"), vec!["aB"]);
+        assert_eq!(ExportConvention::Dashes.apply("a_b-c"), vec!["a_b-c", "a_bC"]);
+        assert_eq!(ExportConvention::DashesOnly.apply("a-b"), vec!["aB"]);
+    }
+}
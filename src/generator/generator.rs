@@ -1,53 +1,879 @@
-use std::{fs::File, path::Path};
+use std::{fs, path::{Path, PathBuf}};
 
 
-use handlebars::{to_json, Handlebars};
-use serde_json::Map;
+use handlebars::{no_escape, Handlebars};
+use log::warn;
+use serde_json::{json, Value};
 
-use crate::parser::scss::ScssFile;
-use crate::generator::templates::Templates;
+use crate::lexer::lexer::Span;
+use crate::parser::ast::Scope;
+use crate::parser::scss::{ClassName, ScssFile};
+use crate::generator::convention::ExportConvention;
+use crate::generator::templates::Template;
+use crate::generator::typescript::{is_binding_name, ts_key};
 
 
+/// First line of every generated declaration. Only files starting with it
+/// are deleted by `--clean` or overwritten without `--force`.
+pub const MARKER: &str = "// @generated by ts-scss-modules";
+
+/// Comment below the marker unless `--banner` is given.
+pub const DEFAULT_BANNER: &str = "Do not edit, run ts-scss-modules to update it.";
+
+/// Whether the declaration file was written by the generator.
+pub fn is_generated(declaration_path: &Path) -> bool {
+	fs::read_to_string(declaration_path)
+		.map(|content| content.lines().next() == Some(MARKER))
+		.unwrap_or(false)
+}
+
+/// Order of the keys in the generated declarations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+	#[default]
+	Alphabetical, // Sorted by key
+	Source,       // First appearance in the file
+}
+
+/// Settings from the command line that change the generated declarations.
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+	pub mode: Scope,                          // Scope of classes outside of :global and :local
+	pub export_convention: ExportConvention, // How names become keys of the styles object
+	pub sort: SortOrder,                      // Order of the classes and of the exports
+	pub named_exports: bool,                  // `export const name: string;` for each key
+	pub keep_default_export: bool,            // Default export next to the named exports
+	pub banner: String,                       // Comment below the marker, none when empty
+	pub source_hash: bool,                    // Hash of the stylesheet in the header
+	pub force: bool,                          // Overwrite declarations without the marker
+	pub out_dir: Option<PathBuf>,             // Declarations mirror the tree from the root here
+}
+
+impl Default for GeneratorOptions {
+	fn default() -> Self {
+		GeneratorOptions {
+			mode: Scope::default(),
+			export_convention: ExportConvention::default(),
+			sort: SortOrder::default(),
+			named_exports: false,
+			keep_default_export: false,
+			banner: DEFAULT_BANNER.to_string(),
+			source_hash: false,
+			force: false,
+			out_dir: None,
+		}
+	}
+}
+
+/// A key of the styles object and where it comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+	pub key: String,           // Name after the export convention
+	pub name: String,          // Class name or :export key in the file
+	pub value: Option<String>, // Value of an :export key
+	pub span: Span,
+}
+
 #[derive(Debug)]
 pub struct Generator {
-	pub templates: Templates,
+	handlebars: Handlebars<'static>,
+	template: String,
+	pub options: GeneratorOptions,
+	pub root: Option<PathBuf>, // Relative paths passed to templates start here
 }
 
 
 impl Generator {
-    pub fn new() -> Self {
-        Generator {
-			templates: Templates::new(),
+    pub fn new(options: GeneratorOptions) -> Self {
+		let mut handlebars = Handlebars::new();
+		// Keys are TypeScript, not HTML, `{{ts_key c}}` takes care of quoting
+		handlebars.register_escape_fn(no_escape);
+		handlebars.register_helper("ts_key", Box::new(ts_key));
+		// Unknown fields are errors instead of empty strings
+		handlebars.set_strict_mode(true);
+
+		Generator {
+			handlebars,
+			template: String::new(),
+			options,
+			root: None,
 		}
+		.with_template(Template::default())
+		.expect("Built-in templates are valid")
     }
 
-    pub fn generate_declaration(&self, scss_file: &ScssFile) -> Result<bool, String> {
-		if scss_file.classes().is_empty() {
-			return Ok(false);
+	/// Renders `template` instead. The template is checked by rendering a
+	/// sample file, so mistakes show up before any file is written.
+	pub fn with_template(mut self, template: Template) -> Result<Self, String> {
+		if self.options.named_exports && template.name == "commonjs" {
+			return Err("The commonjs template declares `export = styles`, which can't be combined with --named-exports".to_string());
 		}
+		if let Err(e) = self.handlebars.register_template_string(&template.name, template.source) {
+			return Err(format!("Invalid template {}: {}", template.name, e));
+		}
+		self.template = template.name;
 
-		let mut handlebars = Handlebars::new();
-		handlebars.register_template_string("default", self.templates.default.clone()).unwrap();
+		// Keys valid as named exports under every convention, so checking the
+		// template logs nothing
+		let sample = ScssFile::parse(".sample {}\n:export {\n  sampleValue: 1px;\n}\n", "sample.module.scss");
+		if let Err(e) = self.render(&sample) {
+			return Err(format!("Invalid template {}: {}", self.template, e));
+		}
+		Ok(self)
+	}
 
-		let declaration_file_path = Path::new(&scss_file.file_path);
-		let mut declaration_file_path_formatted = String::from("");
-		declaration_file_path_formatted.push_str(declaration_file_path.parent().unwrap().to_str().unwrap());
-		declaration_file_path_formatted.push('/');
-		declaration_file_path_formatted.push_str(declaration_file_path.file_name().unwrap().to_str().unwrap());
-		declaration_file_path_formatted.push_str(".d.ts");
+	pub fn with_root(mut self, root: &Path) -> Self {
+		self.root = Some(root.to_path_buf());
+		self
+	}
 
+	/// The locally scoped classes, global ones are not part of the styles
+	/// object.
+	pub fn local_classes(&self, scss_file: &ScssFile) -> Vec<ClassName> {
+		scss_file
+			.classes(self.options.mode)
+			.into_iter()
+			.filter(|class| class.scope == Scope::Local)
+			.collect()
+	}
 
-		let mut outfile = File::create(declaration_file_path_formatted).expect("Could not create file handle.");
+	/// Keys of the local classes after applying the export convention, in
+	/// the configured order. Classes that end up with the same key, like
+	/// `my-button` and `my_button` with `camelCaseOnly`, share it.
+	pub fn class_keys(&self, scss_file: &ScssFile) -> Vec<Key> {
+		let mut keys: Vec<Key> = Vec::new();
+		for class in self.local_classes(scss_file) {
+			for key in self.options.export_convention.apply(&class.name) {
+				match keys.iter().find(|existing| existing.key == key) {
+					Some(existing) if existing.name != class.name => warn!(
+						"{}:{}:{}: class '{}' collides with '{}' as key '{}', both share the key",
+						scss_file.file_path, class.span.line, class.span.column, class.name, existing.name, key
+					),
+					Some(_) => {}
+					None => keys.push(Key {
+						key,
+						name: class.name.clone(),
+						value: None,
+						span: class.span,
+					}),
+				}
+			}
+		}
+		self.sorted(keys)
+	}
 
-		let mut output_data = Map::new();
+	/// Keys of the file's `:export` blocks after applying the export
+	/// convention, without the ones already used by a class or an earlier
+	/// export, in the configured order.
+	pub fn export_keys(&self, scss_file: &ScssFile, classes: &[Key]) -> Vec<Key> {
+		let mut keys: Vec<Key> = Vec::new();
+		for export in scss_file.exports() {
+			for key in self.options.export_convention.apply(&export.name) {
+				if classes.iter().any(|class| class.key == key) {
+					warn!(
+						"{}:{}:{}: :export key '{}' collides with a class of the same name, the key is skipped",
+						scss_file.file_path, export.span.line, export.span.column, key
+					);
+				} else if !keys.iter().any(|existing| existing.key == key) {
+					keys.push(Key {
+						key,
+						name: export.name.clone(),
+						value: Some(export.value.clone()),
+						span: export.span,
+					});
+				}
+			}
+		}
+		self.sorted(keys)
+	}
 
-		output_data.insert("class".to_string(), to_json(Vec::from_iter(&scss_file.classes())));
+	/// Keys exported with `export const`. Keys that are not identifiers or
+	/// are reserved words can't be declared and are skipped.
+	pub fn named_keys(&self, scss_file: &ScssFile, keys: &[Key]) -> Vec<String> {
+		if !self.options.named_exports {
+			return Vec::new();
+		}
+		keys.iter()
+			.filter(|key| {
+				let valid = is_binding_name(&key.key);
+				if !valid {
+					warn!(
+						"{}:{}:{}: '{}' is not a valid name for a named export and is skipped, --export-convention camelCaseOnly avoids most of these",
+						scss_file.file_path, key.span.line, key.span.column, key.key
+					);
+				}
+				valid
+			})
+			.map(|key| key.key.clone())
+			.collect()
+	}
+
+	fn sorted(&self, mut keys: Vec<Key>) -> Vec<Key> {
+		if self.options.sort == SortOrder::Alphabetical {
+			keys.sort_by(|a, b| a.key.cmp(&b.key));
+		}
+		keys
+	}
 
-		let render_result = handlebars.render_to_write("default", &to_json(output_data), &mut outfile);
+	/// Data passed to the template, see `Template` for the fields.
+	fn template_data(&self, scss_file: &ScssFile, classes: &[Key], exports: &[Key]) -> Value {
+		let file_path = Path::new(&scss_file.file_path);
+		let relative_path = self
+			.root
+			.as_deref()
+			.and_then(|root| file_path.strip_prefix(root).ok())
+			.unwrap_or(file_path);
+		let module_name = file_path
+			.file_name()
+			.and_then(|name| name.to_str())
+			.and_then(|name| name.split('.').next())
+			.unwrap_or_default();
+		let locations = |keys: &[Key]| {
+			keys.iter()
+				.map(|key| {
+					json!({
+						"key": key.key,
+						"name": key.name,
+						"value": key.value,
+						"line": key.span.line,
+						"column": key.span.column,
+					})
+				})
+				.collect::<Vec<_>>()
+		};
+
+		let all_keys = [classes, exports].concat();
+		json!({
+			"named_exports": self.options.named_exports,
+			"default_export": !self.options.named_exports || self.options.keep_default_export,
+			"named": self.named_keys(scss_file, &all_keys),
+			"class": classes.iter().map(|key| &key.key).collect::<Vec<_>>(),
+			"export": exports.iter().map(|key| &key.key).collect::<Vec<_>>(),
+			"classes": locations(classes),
+			"exports": locations(exports),
+			"file_path": scss_file.file_path,
+			"relative_path": relative_path.to_string_lossy().replace('\\', "/"),
+			"module_name": module_name,
+		})
+	}
+
+	/// Renders the declaration of the file, `None` when it has no keys.
+	pub fn render(&self, scss_file: &ScssFile) -> Result<Option<String>, String> {
+		let classes = self.class_keys(scss_file);
+		let exports = self.export_keys(scss_file, &classes);
+		if classes.is_empty() && exports.is_empty() {
+			return Ok(None);
+		}
 
-		match render_result {
-			Ok(_) => Ok(true),
+		let data = self.template_data(scss_file, &classes, &exports);
+		match self.handlebars.render(&self.template, &data) {
+			Ok(declaration) => Ok(Some(self.header(scss_file) + &declaration)),
 			Err(e) => Err(format!("Error rendering template: {}", e)),
 		}
 	}
+
+	/// The marker, the banner and the source hash, each on a comment line.
+	fn header(&self, scss_file: &ScssFile) -> String {
+		let mut header = format!("{}\n", MARKER);
+		for line in self.options.banner.lines() {
+			header.push_str(format!("// {}", line).trim_end());
+			header.push('\n');
+		}
+		if self.options.source_hash {
+			header.push_str(&format!("// source-hash: {:016x}\n", scss_file.source_hash));
+		}
+		header
+	}
+
+	/// Path of the declaration file, like `card.module.scss.d.ts` next to
+	/// the stylesheet or at the same relative path in the output directory.
+	pub fn declaration_path(&self, scss_file: &ScssFile) -> PathBuf {
+		self.declaration_path_for(Path::new(&scss_file.file_path))
+	}
+
+	/// Path of the declaration file of the stylesheet at `file_path`.
+	pub fn declaration_path_for(&self, file_path: &Path) -> PathBuf {
+		let relative_path = self
+			.root
+			.as_deref()
+			.and_then(|root| file_path.strip_prefix(root).ok());
+		let directory = match (&self.options.out_dir, relative_path) {
+			(Some(out_dir), Some(relative_path)) => out_dir.join(relative_path),
+			_ => file_path.to_path_buf(),
+		};
+		PathBuf::from(format!("{}.d.ts", directory.display()))
+	}
+
+	/// The stylesheet a declaration file belongs to, the reverse of
+	/// `declaration_path`.
+	pub fn source_path(&self, declaration_path: &Path) -> PathBuf {
+		let declaration_path = declaration_path.to_string_lossy();
+		let path = Path::new(declaration_path.trim_end_matches(".d.ts"));
+		match (&self.options.out_dir, &self.root) {
+			(Some(out_dir), Some(root)) => match path.strip_prefix(out_dir) {
+				Ok(relative_path) => root.join(relative_path),
+				Err(_) => path.to_path_buf(),
+			},
+			_ => path.to_path_buf(),
+		}
+	}
+
+	/// Directory containing the declaration files of the stylesheets in
+	/// `directory`, searched for orphans.
+	pub fn declaration_dir(&self, directory: &Path) -> PathBuf {
+		let relative_path = self
+			.root
+			.as_deref()
+			.and_then(|root| directory.strip_prefix(root).ok());
+		match (&self.options.out_dir, relative_path) {
+			(Some(out_dir), Some(relative_path)) => out_dir.join(relative_path),
+			_ => directory.to_path_buf(),
+		}
+	}
+
+	/// Renders the declaration and compares it with the existing file, the
+	/// content is returned when the file has to be written.
+	fn compare(&self, scss_file: &ScssFile) -> Result<(Outcome, Option<String>), String> {
+		let declaration_path = self.declaration_path(scss_file);
+		let Some(declaration) = self.render(scss_file)? else {
+			if is_generated(&declaration_path) {
+				return Ok((Outcome::Orphaned, None));
+			}
+			return Ok((Outcome::Skipped, None));
+		};
+
+		match fs::read(&declaration_path) {
+			Ok(existing) if existing == declaration.as_bytes() => Ok((Outcome::Unchanged, None)),
+			Ok(_) if !self.options.force && !is_generated(&declaration_path) => Err(format!(
+				"{} was not generated by ts-scss-modules and is kept, use --force to overwrite it",
+				declaration_path.display()
+			)),
+			Ok(_) => Ok((Outcome::Updated, Some(declaration))),
+			Err(_) => Ok((Outcome::Created, Some(declaration))),
+		}
+	}
+
+	/// What `generate_declaration` would do, without writing anything.
+	pub fn check_declaration(&self, scss_file: &ScssFile) -> Result<Outcome, String> {
+		self.compare(scss_file).map(|(outcome, _)| outcome)
+	}
+
+	/// Writes the declaration of the file. The file is left alone when it
+	/// already has the same content, so its mtime only changes with it.
+    pub fn generate_declaration(&self, scss_file: &ScssFile) -> Result<Outcome, String> {
+		let (outcome, declaration) = self.compare(scss_file)?;
+		let Some(declaration) = declaration else {
+			return Ok(outcome);
+		};
+
+		let declaration_path = self.declaration_path(scss_file);
+		if let Some(directory) = declaration_path.parent() {
+			if let Err(e) = fs::create_dir_all(directory) {
+				return Err(format!("Error creating {}: {}", directory.display(), e));
+			}
+		}
+		match fs::write(&declaration_path, declaration) {
+			Ok(_) => Ok(outcome),
+			Err(e) => Err(format!("Error writing {}: {}", declaration_path.display(), e)),
+		}
+	}
+
+	/// Deletes an orphaned declaration, unless it was not written by the
+	/// generator. With `dry_run` nothing is deleted.
+	pub fn remove_declaration(&self, declaration_path: &Path, dry_run: bool) -> Result<Outcome, String> {
+		if !is_generated(declaration_path) {
+			warn!("{} was not generated by ts-scss-modules and is kept", declaration_path.display());
+			return Ok(Outcome::Orphaned);
+		}
+		if dry_run {
+			return Ok(Outcome::Removed);
+		}
+		match fs::remove_file(declaration_path) {
+			Ok(_) => Ok(Outcome::Removed),
+			Err(e) => Err(format!("Error removing {}: {}", declaration_path.display(), e)),
+		}
+	}
+}
+
+/// What happened to the declaration file of a stylesheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+	Skipped,   // No classes or exports, nothing is written
+	Created,
+	Updated,
+	Unchanged, // Same content, the file is not touched
+	Orphaned,  // A declaration exists, but nothing would be generated for it
+	Removed,   // An orphaned declaration was deleted by --clean
+}
+
+impl Outcome {
+	/// How `--check` reports the declaration file.
+	pub fn check_message(&self) -> &'static str {
+		match self {
+			Outcome::Created => "missing",
+			Outcome::Updated => "out of date",
+			Outcome::Orphaned => "orphaned",
+			Outcome::Removed => "removed",
+			Outcome::Skipped | Outcome::Unchanged => "up to date",
+		}
+	}
+}
+
+/// Number of files per outcome, summed up over the worker threads.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Summary {
+	pub created: usize,
+	pub updated: usize,
+	pub unchanged: usize,
+	pub skipped: usize,
+	pub orphaned: usize,
+	pub removed: usize,
+	pub failed: usize,
+	pub changes: Vec<(PathBuf, Outcome)>, // Declarations created, updated, orphaned or removed
+}
+
+impl Summary {
+	pub fn add(&mut self, declaration_path: PathBuf, result: &Result<Outcome, String>) {
+		match result {
+			Ok(Outcome::Created) => self.created += 1,
+			Ok(Outcome::Updated) => self.updated += 1,
+			Ok(Outcome::Unchanged) => self.unchanged += 1,
+			Ok(Outcome::Skipped) => self.skipped += 1,
+			Ok(Outcome::Orphaned) => self.orphaned += 1,
+			Ok(Outcome::Removed) => self.removed += 1,
+			Err(_) => self.failed += 1,
+		}
+		if let Ok(outcome @ (Outcome::Created | Outcome::Updated | Outcome::Orphaned | Outcome::Removed)) = result {
+			self.changes.push((declaration_path, *outcome));
+		}
+	}
+
+	pub fn merge(&mut self, other: Summary) {
+		self.created += other.created;
+		self.updated += other.updated;
+		self.unchanged += other.unchanged;
+		self.skipped += other.skipped;
+		self.orphaned += other.orphaned;
+		self.removed += other.removed;
+		self.failed += other.failed;
+		self.changes.extend(other.changes);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use crate::generator::templates::BUILTIN_TEMPLATES;
+
+    /// Keys of the `Styles` type, checked the way tsc would read them:
+    /// plain identifiers that are not reserved words or single-quoted
+    /// strings with escaped quotes.
+    fn validate_keys(declaration: &str) -> Result<Vec<String>, String> {
+        let identifier = Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").unwrap();
+        let quoted = Regex::new(r"^'(?:[^'\\\n]|\\.)*'$").unwrap();
+        let body = declaration
+            .split_once("export type Styles = {\n")
+            .and_then(|(_, rest)| rest.split_once("\n}"))
+            .map(|(body, _)| body)
+            .ok_or("No Styles type")?;
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let key = line
+                    .trim()
+                    .strip_suffix(": string;")
+                    .ok_or(format!("Not a property: {line}"))?;
+                let valid = quoted.is_match(key)
+                    || (identifier.is_match(key) && !["class", "default", "delete"].contains(&key));
+                if valid {
+                    Ok(key.to_string())
+                } else {
+                    Err(format!("Invalid key: {key}"))
+                }
+            })
+            .collect()
+    }
+
+    fn keys(keys: &[Key]) -> Vec<&str> {
+        keys.iter().map(|key| key.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_export_keys_skip_classes_and_duplicates() {
+        let scss_file = ScssFile::parse(
+            ".primary {}\n:export {\n  primary: red;\n  gap: 4px;\n  gap: 8px;\n}\n",
+            "a.scss",
+        );
+        let generator = Generator::new(GeneratorOptions::default());
+        let classes = generator.class_keys(&scss_file);
+        assert_eq!(keys(&generator.export_keys(&scss_file, &classes)), vec!["gap"]);
+    }
+
+    #[test]
+    fn test_export_convention_is_applied_to_classes_and_exports() {
+        let scss_file = ScssFile::parse(
+            ".my-button {}\n.my_button {}\n:global(.app-root) {}\n:export {\n  my-button: red;\n  font-size: 12px;\n}\n",
+            "a.scss",
+        );
+        let generator = Generator::new(GeneratorOptions {
+            export_convention: ExportConvention::CamelCaseOnly,
+            ..GeneratorOptions::default()
+        });
+        let classes = generator.class_keys(&scss_file);
+        // The first class keeps the key, the collision is warned about
+        assert_eq!(keys(&classes), vec!["myButton"]);
+        assert_eq!(classes[0].name, "my-button");
+        assert_eq!(keys(&generator.export_keys(&scss_file, &classes)), vec!["fontSize"]);
+
+        let generator = Generator::new(GeneratorOptions {
+            export_convention: ExportConvention::Dashes,
+            ..GeneratorOptions::default()
+        });
+        assert_eq!(
+            keys(&generator.class_keys(&scss_file)),
+            vec!["my-button", "myButton", "my_button"]
+        );
+    }
+
+    #[test]
+    fn test_generated_keys_are_valid_typescript() {
+        let scss_file = ScssFile::parse(
+            ".my-class {}\n.2col {}\n.is-active {}\n.default {}\n.class {}\n.delete {}\n.button {}\n:export {\n  primary-color: red;\n}\n",
+            "a.scss",
+        );
+        let generator = Generator::new(GeneratorOptions::default());
+        let declaration = generator.render(&scss_file).unwrap().unwrap();
+        assert_eq!(
+            validate_keys(&declaration).unwrap(),
+            vec![
+                "'2col'",
+                "button",
+                "'class'",
+                "'default'",
+                "'delete'",
+                "'is-active'",
+                "'my-class'",
+                "'primary-color'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_keys_rejects_unquoted_names() {
+        let declaration = "export type Styles = {\n  my-class: string;\n}\n";
+        assert!(validate_keys(declaration).is_err());
+    }
+
+    #[test]
+    fn test_sort_order() {
+        let scss_file = ScssFile::parse(
+            ".zebra {}\n.apple {}\n.mango {}\n:export {\n  z-index: 2;\n  gap: 4px;\n}\n",
+            "a.scss",
+        );
+        let generator = Generator::new(GeneratorOptions::default());
+        let classes = generator.class_keys(&scss_file);
+        assert_eq!(keys(&classes), vec!["apple", "mango", "zebra"]);
+        assert_eq!(keys(&generator.export_keys(&scss_file, &classes)), vec!["gap", "z-index"]);
+
+        let generator = Generator::new(GeneratorOptions {
+            sort: SortOrder::Source,
+            ..GeneratorOptions::default()
+        });
+        let classes = generator.class_keys(&scss_file);
+        assert_eq!(keys(&classes), vec!["zebra", "apple", "mango"]);
+        assert_eq!(keys(&generator.export_keys(&scss_file, &classes)), vec!["z-index", "gap"]);
+    }
+
+    #[test]
+    fn test_repeated_generation_is_identical() {
+        let content = fs::read_to_string(format!(
+            "{}/tests/fixtures/card.module.scss",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        for sort in [SortOrder::Alphabetical, SortOrder::Source] {
+            let render = || {
+                let generator = Generator::new(GeneratorOptions {
+                    sort,
+                    ..GeneratorOptions::default()
+                });
+                generator.render(&ScssFile::parse(&content, "card.module.scss")).unwrap().unwrap()
+            };
+            let first = render();
+            for _ in 0..10 {
+                assert_eq!(render().as_bytes(), first.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_template_data_model() {
+        let template = Template {
+            name: "model".to_string(),
+            source: "{{module_name}} {{relative_path}}\n{{#each classes}}{{key}}={{name}}@{{line}}:{{column}}\n{{/each}}{{#each exports}}{{key}}={{value}}@{{line}}\n{{/each}}".to_string(),
+        };
+        let options = GeneratorOptions {
+            export_convention: ExportConvention::CamelCaseOnly,
+            banner: String::new(),
+            ..GeneratorOptions::default()
+        };
+        let generator = Generator::new(options)
+            .with_template(template)
+            .unwrap()
+            .with_root(Path::new("/app"));
+        let scss_file = ScssFile::parse(
+            ".card {\n  &__title {}\n}\n:export {\n  gap-size: 4px;\n}\n",
+            "/app/src/card.module.scss",
+        );
+        assert_eq!(
+            generator.render(&scss_file).unwrap().unwrap(),
+            "// @generated by ts-scss-modules\ncard src/card.module.scss\ncard=card@1:1\ncardTitle=card__title@2:3\ngapSize=4px@5\n"
+        );
+    }
+
+    #[test]
+    fn test_builtin_templates_render() {
+        let scss_file = ScssFile::parse(".my-class {}\n", "a.scss");
+        for (name, _) in BUILTIN_TEMPLATES {
+            let template = Template::builtin(name).unwrap();
+            let generator = Generator::new(GeneratorOptions::default())
+                .with_template(template)
+                .unwrap();
+            let declaration = generator.render(&scss_file).unwrap().unwrap();
+            assert!(declaration.contains("'my-class': string;"), "{name}: {declaration}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        let template = |source: &str| Template {
+            name: "custom.hbs".to_string(),
+            source: source.to_string(),
+        };
+        let error = Generator::new(GeneratorOptions::default())
+            .with_template(template("{{#each class}}"))
+            .unwrap_err();
+        assert!(error.starts_with("Invalid template custom.hbs"), "{error}");
+        let error = Generator::new(GeneratorOptions::default())
+            .with_template(template("{{clases}}"))
+            .unwrap_err();
+        assert!(error.starts_with("Invalid template custom.hbs"), "{error}");
+    }
+
+    #[test]
+    fn test_commonjs_template_rejects_named_exports() {
+        let error = Generator::new(GeneratorOptions {
+            named_exports: true,
+            ..GeneratorOptions::default()
+        })
+        .with_template(Template::builtin("commonjs").unwrap())
+        .unwrap_err();
+        assert!(error.contains("--named-exports"), "{error}");
+    }
+
+    #[test]
+    fn test_named_exports() {
+        let scss_file = ScssFile::parse(
+            ".button {}\n.my-class {}\n.default {}\n.type {}\n:export {\n  gap: 4px;\n}\n",
+            "a.scss",
+        );
+        let generator = Generator::new(GeneratorOptions {
+            named_exports: true,
+            banner: String::new(),
+            ..GeneratorOptions::default()
+        });
+        assert_eq!(
+            generator.render(&scss_file).unwrap().unwrap(),
+            "// @generated by ts-scss-modules\nexport const button: string;\nexport const type: string;\nexport const gap: string;\n"
+        );
+    }
+
+    #[test]
+    fn test_named_exports_without_valid_names() {
+        // Still a module, so `import { x } from './a.module.scss'` type-checks
+        let scss_file = ScssFile::parse(".class {}\n.my-btn--x {}\n", "a.scss");
+        for template in ["default", "interface"] {
+            let generator = Generator::new(GeneratorOptions {
+                named_exports: true,
+                banner: String::new(),
+                ..GeneratorOptions::default()
+            })
+            .with_template(Template::builtin(template).unwrap())
+            .unwrap();
+            assert_eq!(
+                generator.render(&scss_file).unwrap().unwrap(),
+                "// @generated by ts-scss-modules\nexport {};\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_named_exports_with_default_export() {
+        let scss_file = ScssFile::parse(".button {}\n.my-class {}\n", "a.scss");
+        let generator = Generator::new(GeneratorOptions {
+            named_exports: true,
+            keep_default_export: true,
+            banner: String::new(),
+            ..GeneratorOptions::default()
+        });
+        assert_eq!(
+            generator.render(&scss_file).unwrap().unwrap(),
+            "// @generated by ts-scss-modules\nexport const button: string;\n\nexport type Styles = {\n  button: string;\n  'my-class': string;\n}\n\nexport type ClassNames = keyof Styles;\n\ndeclare const styles: Styles;\n\nexport default styles;\n"
+        );
+    }
+
+    #[test]
+    fn test_unchanged_declarations_are_not_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.module.scss");
+        fs::write(&path, ".button {}\n").unwrap();
+        let generator = Generator::new(GeneratorOptions::default());
+        let declaration_path = generator.declaration_path(&ScssFile::new(&path).unwrap());
+        let mut summary = Summary::default();
+
+        let result = generator.generate_declaration(&ScssFile::new(&path).unwrap());
+        assert_eq!(result, Ok(Outcome::Created));
+        summary.add(declaration_path.clone(), &result);
+        let modified = fs::metadata(&declaration_path).unwrap().modified().unwrap();
+
+        let result = generator.generate_declaration(&ScssFile::new(&path).unwrap());
+        assert_eq!(result, Ok(Outcome::Unchanged));
+        summary.add(declaration_path.clone(), &result);
+        assert_eq!(fs::metadata(&declaration_path).unwrap().modified().unwrap(), modified);
+
+        fs::write(&path, ".button {}\n.icon {}\n").unwrap();
+        let result = generator.generate_declaration(&ScssFile::new(&path).unwrap());
+        assert_eq!(result, Ok(Outcome::Updated));
+        summary.add(declaration_path.clone(), &result);
+        assert!(fs::read_to_string(&declaration_path).unwrap().contains("icon: string;"));
+
+        fs::write(dir.path().join("b.module.scss"), "div {}\n").unwrap();
+        let result = generator.generate_declaration(&ScssFile::new(&dir.path().join("b.module.scss")).unwrap());
+        assert_eq!(result, Ok(Outcome::Skipped));
+        summary.add(dir.path().join("b.module.scss.d.ts"), &result);
+        assert_eq!(
+            summary,
+            Summary {
+                created: 1,
+                updated: 1,
+                unchanged: 1,
+                skipped: 1,
+                orphaned: 0,
+                removed: 0,
+                failed: 0,
+                changes: vec![
+                    (declaration_path.clone(), Outcome::Created),
+                    (declaration_path, Outcome::Updated),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_declaration_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.module.scss");
+        fs::write(&path, ".button {}\n").unwrap();
+        let generator = Generator::new(GeneratorOptions::default());
+        let declaration_path = generator.declaration_path(&ScssFile::new(&path).unwrap());
+
+        assert_eq!(generator.check_declaration(&ScssFile::new(&path).unwrap()), Ok(Outcome::Created));
+        assert!(!declaration_path.exists());
+
+        fs::write(&declaration_path, format!("{}\nexport type Styles = {{}}\n", MARKER)).unwrap();
+        assert_eq!(generator.check_declaration(&ScssFile::new(&path).unwrap()), Ok(Outcome::Updated));
+
+        generator.generate_declaration(&ScssFile::new(&path).unwrap()).unwrap();
+        assert_eq!(generator.check_declaration(&ScssFile::new(&path).unwrap()), Ok(Outcome::Unchanged));
+
+        fs::write(&path, "div {}\n").unwrap();
+        assert_eq!(generator.check_declaration(&ScssFile::new(&path).unwrap()), Ok(Outcome::Orphaned));
+        assert_eq!(generator.generate_declaration(&ScssFile::new(&path).unwrap()), Ok(Outcome::Orphaned));
+        assert!(declaration_path.exists());
+    }
+
+    #[test]
+    fn test_remove_declaration_only_removes_generated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let generated = dir.path().join("a.module.scss.d.ts");
+        let handwritten = dir.path().join("b.module.scss.d.ts");
+        fs::write(&generated, format!("{}\nexport default {{}};\n", MARKER)).unwrap();
+        fs::write(&handwritten, "export default {};\n").unwrap();
+        let generator = Generator::new(GeneratorOptions::default());
+
+        assert_eq!(generator.remove_declaration(&generated, true), Ok(Outcome::Removed));
+        assert!(generated.exists());
+        assert_eq!(generator.remove_declaration(&generated, false), Ok(Outcome::Removed));
+        assert!(!generated.exists());
+        assert_eq!(generator.remove_declaration(&handwritten, false), Ok(Outcome::Orphaned));
+        assert!(handwritten.exists());
+    }
+
+    #[test]
+    fn test_header() {
+        let scss_file = ScssFile::parse(".button {}\n", "a.scss");
+        let generator = Generator::new(GeneratorOptions::default());
+        assert!(generator.render(&scss_file).unwrap().unwrap().starts_with(
+            "// @generated by ts-scss-modules\n// Do not edit, run ts-scss-modules to update it.\nexport type Styles = {\n"
+        ));
+
+        let generator = Generator::new(GeneratorOptions {
+            banner: "Generated file\n\nSee README.md".to_string(),
+            source_hash: true,
+            ..GeneratorOptions::default()
+        });
+        assert!(generator.render(&scss_file).unwrap().unwrap().starts_with(
+            "// @generated by ts-scss-modules\n// Generated file\n//\n// See README.md\n// source-hash: 87915bdff5f1e60b\nexport type Styles = {\n"
+        ));
+    }
+
+    #[test]
+    fn test_handwritten_declarations_need_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.module.scss");
+        fs::write(&path, ".button {}\n").unwrap();
+        let declaration_path = dir.path().join("a.module.scss.d.ts");
+        fs::write(&declaration_path, "export default {};\n").unwrap();
+
+        let generator = Generator::new(GeneratorOptions::default());
+        let error = generator.generate_declaration(&ScssFile::new(&path).unwrap()).unwrap_err();
+        assert!(error.ends_with("was not generated by ts-scss-modules and is kept, use --force to overwrite it"));
+        assert_eq!(fs::read_to_string(&declaration_path).unwrap(), "export default {};\n");
+
+        let generator = Generator::new(GeneratorOptions {
+            force: true,
+            ..GeneratorOptions::default()
+        });
+        assert_eq!(generator.generate_declaration(&ScssFile::new(&path).unwrap()), Ok(Outcome::Updated));
+        assert!(is_generated(&declaration_path));
+    }
+
+    #[test]
+    fn test_out_dir_mirrors_the_source_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("app");
+        let out_dir = dir.path().join("types/generated");
+        fs::create_dir_all(root.join("src/components")).unwrap();
+        let path = root.join("src/components/a.module.scss");
+        fs::write(&path, ".button {}\n").unwrap();
+
+        let generator = Generator::new(GeneratorOptions {
+            out_dir: Some(out_dir.clone()),
+            ..GeneratorOptions::default()
+        })
+        .with_root(&root);
+        let scss_file = ScssFile::new(&path).unwrap();
+        let declaration_path = out_dir.join("src/components/a.module.scss.d.ts");
+        assert_eq!(generator.declaration_path(&scss_file), declaration_path);
+        assert_eq!(generator.source_path(&declaration_path), path);
+        assert_eq!(generator.declaration_dir(&root), out_dir);
+        assert_eq!(generator.declaration_dir(&root.join("src")), out_dir.join("src"));
+
+        assert_eq!(generator.generate_declaration(&scss_file), Ok(Outcome::Created));
+        assert!(declaration_path.exists());
+        assert!(!root.join("src/components/a.module.scss.d.ts").exists());
+        assert_eq!(generator.check_declaration(&scss_file), Ok(Outcome::Unchanged));
+    }
 }
@@ -1,2 +1,5 @@
+pub(crate) mod convention;
+#[allow(clippy::module_inception)]
 pub(crate) mod generator;
 pub(crate) mod templates;
+pub(crate) mod typescript;
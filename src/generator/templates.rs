@@ -1,15 +1,49 @@
-#[derive(Debug)]
-pub struct Templates {
-    /// Default d.ts. file template.
-    pub default: String,
+use std::fs;
+
+/// Default d.ts. file template, a `Styles` type and a default export.
+const DEFAULT: &str = r"{{#each named as |n| }}
+export const {{n}}: string;
+{{/each}}
+{{#if default_export}}
+{{#if named_exports}}
+
+{{/if}}
+export type Styles = {
+{{#each class as |c| }}
+  {{ts_key c}}: string;
+{{/each}}
+{{#each export as |e| }}
+  {{ts_key e}}: string;
+{{/each}}
 }
 
-impl Templates {
-    pub fn new() -> Self {
-        let default = String::from(
-            r"export type Styles = {
+export type ClassNames = keyof Styles;
+
+declare const styles: Styles;
+
+export default styles;
+{{/if}}
+{{#unless default_export}}
+{{#unless named}}
+export {};
+{{/unless}}
+{{/unless}}
+";
+
+/// A readonly `Styles` interface instead of a type alias.
+const INTERFACE: &str = r"{{#each named as |n| }}
+export const {{n}}: string;
+{{/each}}
+{{#if default_export}}
+{{#if named_exports}}
+
+{{/if}}
+export interface Styles {
 {{#each class as |c| }}
-  {{c}}: string;
+  readonly {{ts_key c}}: string;
+{{/each}}
+{{#each export as |e| }}
+  readonly {{ts_key e}}: string;
 {{/each}}
 }
 
@@ -18,9 +52,108 @@ export type ClassNames = keyof Styles;
 declare const styles: Styles;
 
 export default styles;
-",
-        );
+{{/if}}
+{{#unless default_export}}
+{{#unless named}}
+export {};
+{{/unless}}
+{{/unless}}
+";
+
+/// `export =` for projects importing styles with `import styles = require()`,
+/// which can't be combined with named exports.
+const COMMONJS: &str = r"declare const styles: {
+{{#each class as |c| }}
+  readonly {{ts_key c}}: string;
+{{/each}}
+{{#each export as |e| }}
+  readonly {{ts_key e}}: string;
+{{/each}}
+};
+
+export = styles;
+";
+
+/// Templates selectable by name with `--template`.
+pub const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("default", DEFAULT),
+    ("interface", INTERFACE),
+    ("commonjs", COMMONJS),
+];
+
+/// A Handlebars template for the d.ts. files. Templates are rendered with:
+///
+/// - `class`: keys of the local classes
+/// - `export`: keys of the `:export` blocks
+/// - `classes`: the class keys as `{ key, name, line, column }`, where `name`
+///   is the class before the export convention and `line`/`column` is its
+///   first appearance
+/// - `exports`: the export keys as `{ key, name, value, line, column }`
+/// - `file_path`: path of the SCSS file
+/// - `relative_path`: path of the SCSS file relative to `--path`
+/// - `module_name`: file name without extensions, like `card` for
+///   `card.module.scss`
+/// - `named`: keys to declare with `export const` for `--named-exports`
+/// - `named_exports`, `default_export`: whether named exports and the
+///   default export are wanted
+///
+/// and the `ts_key` helper, which quotes keys that are not identifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    pub source: String,
+}
+
+impl Template {
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_TEMPLATES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(name, source)| Template {
+                name: name.to_string(),
+                source: source.to_string(),
+            })
+    }
+
+    /// Loads a built-in template by name, or a template file by path.
+    pub fn load(name_or_path: &str) -> Result<Self, String> {
+        if let Some(template) = Self::builtin(name_or_path) {
+            return Ok(template);
+        }
+        match fs::read_to_string(name_or_path) {
+            Ok(source) => Ok(Template {
+                name: name_or_path.to_string(),
+                source,
+            }),
+            Err(e) => Err(format!(
+                "Could not read template {}: {} (built-in templates: {})",
+                name_or_path,
+                e,
+                BUILTIN_TEMPLATES
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::builtin("default").unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Templates { default }
+    #[test]
+    fn test_load_builtin_and_missing_templates() {
+        assert_eq!(Template::load("interface").unwrap().source, INTERFACE);
+        let error = Template::load("does/not/exist.hbs").unwrap_err();
+        assert!(error.starts_with("Could not read template does/not/exist.hbs"));
+        assert!(error.ends_with("(built-in templates: default, interface, commonjs)"));
     }
 }
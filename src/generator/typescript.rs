@@ -0,0 +1,99 @@
+use handlebars::handlebars_helper;
+
+/// Reserved words of JavaScript in strict mode and names TypeScript does not
+/// allow for module level declarations.
+const RESERVED_WORDS: &[&str] = &[
+    "arguments", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "enum", "eval", "export", "extends", "false", "finally",
+    "for", "function", "if", "implements", "import", "in", "instanceof", "interface", "let", "new",
+    "null", "package", "private", "protected", "public", "return", "static", "super", "switch",
+    "this", "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "with", "yield",
+];
+
+/// Keywords that are only special in some places, quoted as keys anyway so
+/// the declarations stay readable and valid for every consumer.
+const CONTEXTUAL_KEYWORDS: &[&str] = &[
+    "any", "as", "boolean", "constructor", "declare", "get", "module", "never", "number",
+    "require", "set", "string", "symbol", "type", "unknown",
+];
+
+pub fn is_reserved_word(name: &str) -> bool {
+    RESERVED_WORDS.contains(&name)
+}
+
+/// Whether `name` can be declared with `export const`, like `button` but not
+/// `my-class` or `default`.
+pub fn is_binding_name(name: &str) -> bool {
+    is_identifier(name) && !is_reserved_word(name)
+}
+
+/// Whether `name` is an IdentifierName, like `button` or `$primary`.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first == '$' || first == '_' || first.is_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c == '$' || c == '_' || c == '\u{200c}' || c == '\u{200d}' || c.is_alphanumeric())
+}
+
+/// Key of a property in an object type: the name itself when it is a valid
+/// identifier, a quoted string otherwise, like `'my-class'` or `'default'`.
+pub fn property_key(name: &str) -> String {
+    if is_binding_name(name) && !CONTEXTUAL_KEYWORDS.contains(&name) {
+        return name.to_string();
+    }
+    let mut key = String::from('\'');
+    for c in name.chars() {
+        match c {
+            '\'' => key.push_str("\\'"),
+            '\\' => key.push_str("\\\\"),
+            '\n' => key.push_str("\\n"),
+            '\r' => key.push_str("\\r"),
+            c => key.push(c),
+        }
+    }
+    key.push('\'');
+    key
+}
+
+// {{ts_key name}} renders `name` as a property key
+handlebars_helper!(ts_key: |name: str| property_key(name));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_identifier() {
+        assert!(is_identifier("button"));
+        assert!(is_identifier("_private"));
+        assert!(is_identifier("$primary"));
+        assert!(is_identifier("col2"));
+        assert!(is_identifier("größe"));
+        assert!(!is_identifier(""));
+        assert!(!is_identifier("2col"));
+        assert!(!is_identifier("my-class"));
+        assert!(!is_identifier("a b"));
+    }
+
+    #[test]
+    fn test_is_binding_name() {
+        assert!(is_binding_name("button"));
+        assert!(is_binding_name("type"));
+        assert!(!is_binding_name("default"));
+        assert!(!is_binding_name("my-class"));
+    }
+
+    #[test]
+    fn test_property_key() {
+        assert_eq!(property_key("button"), "button");
+        assert_eq!(property_key("my-class"), "'my-class'");
+        assert_eq!(property_key("2col"), "'2col'");
+        assert_eq!(property_key("default"), "'default'");
+        assert_eq!(property_key("delete"), "'delete'");
+        assert_eq!(property_key("type"), "'type'");
+        assert_eq!(property_key("it's"), "'it\\'s'");
+        assert_eq!(property_key("a\\b"), "'a\\\\b'");
+    }
+}
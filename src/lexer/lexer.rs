@@ -35,21 +35,47 @@ pub enum TokenKind {
     Import,           // For @import or @use directive
     Include,          // For @include directive
     Class(bool),      // true for nested classes, false for regular classes
+    Parent,           // For the parent selector &, with a suffix like &__title
     Mixin,            // For @mixin directive
     Variable,         // For variables like $primary
     CssVariable,      // For CSS variables like --primary-color
     Media,            // For @media directive
+    AtKeyword,        // For other directives like @keyframes or @extend
     Property(String), // For properties like color, font-size
     Comment,          // For comments
     Op(Operator),     // Operators like +, {, }, (, ), :, ;
     Indent(usize),    // Indentation level
-    EOF,              // End of file
+    Interpolation,    // For Sass interpolation like #{$name}
+    Unknown,          // Characters the lexer does not understand, like > or [
+    #[allow(clippy::upper_case_acronyms)]
+    EOF, // End of file
+}
+
+/// Location of a token in the source. Offsets are in bytes, `line` and
+/// `column` are 1-based and count characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,  // Byte offset of the first character
+    pub end: usize,    // Byte offset just past the last character
+    pub line: usize,   // Line of the first character, starting at 1
+    pub column: usize, // Column of the first character, starting at 1
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Token {
     pub kind: TokenKind,
     pub value: String,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, value: String) -> Self {
+        Token {
+            kind,
+            value,
+            span: Span::default(),
+        }
+    }
 }
 
 impl Display for TokenKind {
@@ -60,22 +86,48 @@ impl Display for TokenKind {
             TokenKind::Include => write!(f, "<include>"),
             TokenKind::Mixin => write!(f, "<mixin>"),
             TokenKind::Media => write!(f, "<media>"),
+            TokenKind::AtKeyword => write!(f, "<at-keyword>"),
             TokenKind::Variable => write!(f, "<variable>"),
             TokenKind::CssVariable => write!(f, "<css-variable>"),
             TokenKind::Comment => write!(f, "<comment>"),
             TokenKind::Property(prop) => write!(f, "<property: {prop}>"),
             TokenKind::Class(nested) => write!(f, "<class:{nested}>"),
+            TokenKind::Parent => write!(f, "<parent>"),
             TokenKind::Op(operator) => write!(f, "<operator: {operator}>"),
             TokenKind::Indent(indent) => write!(f, "<indent: {indent}>"),
+            TokenKind::Interpolation => write!(f, "<interpolation>"),
+            TokenKind::Unknown => write!(f, "<unknown>"),
             TokenKind::EOF => write!(f, "<EOF>"),
         }
     }
 }
 
+/// A problem found while lexing. The lexer records it and keeps going.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file_path: String,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file_path, self.span.line, self.span.column, self.message
+        )
+    }
+}
+
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
     current_char: Option<char>,
     position: usize,
+    line: usize,
+    column: usize,
+    file_path: String,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Iterator for Lexer<'a> {
@@ -84,7 +136,7 @@ impl<'a> Iterator for Lexer<'a> {
         match self.next_token() {
             Token {
                 kind: TokenKind::EOF,
-                value: _,
+                ..
             } => None,
             token => Some(token),
         }
@@ -99,29 +151,44 @@ impl<'a> Lexer<'a> {
             input: chars,
             current_char,
             position: 0,
+            line: 1,
+            column: 1,
+            file_path: String::new(),
+            diagnostics: Vec::new(),
         }
     }
 
+    /// Sets the path reported in diagnostics.
+    pub fn with_file_path(mut self, file_path: &str) -> Self {
+        self.file_path = file_path.to_string();
+        self
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
     pub fn next_token(&mut self) -> Token {
+        let (start, line, column) = (self.position, self.line, self.column);
+        let mut token = self.lex_token();
+        token.span = Span {
+            start,
+            end: self.position,
+            line,
+            column,
+        };
+        token
+    }
+
+    fn lex_token(&mut self) -> Token {
         let c = match self.current_char {
-            None => {
-                return Token {
-                    kind: TokenKind::EOF,
-                    value: String::new(),
-                }
-            }
+            None => return Token::new(TokenKind::EOF, String::new()),
             Some(c) => c,
         };
 
-        let next = match self.peek() {
-            None => {
-                return Token {
-                    kind: TokenKind::EOF,
-                    value: String::new(),
-                }
-            }
-            Some(&next) => next,
-        };
+        // The last character of the input has no successor, use '\0' so it
+        // still gets tokenized instead of being dropped.
+        let next = self.peek().copied().unwrap_or('\0');
 
         self.resolve_char_as_token(c, next)
     }
@@ -130,17 +197,11 @@ impl<'a> Lexer<'a> {
         match (c, next) {
             ('\n', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::NewLine),
-                    value: String::from("\\n"),
-                }
+                Token::new(TokenKind::Op(Operator::NewLine), String::from("\\n"))
             }
             ('\r', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::NewLine),
-                    value: String::from("\\r"),
-                }
+                Token::new(TokenKind::Op(Operator::NewLine), String::from("\\r"))
             }
             (' ', _) => self.consume_indentation(),
             ('\t', _) => self.consume_indentation(),
@@ -148,75 +209,60 @@ impl<'a> Lexer<'a> {
             ('/', '*') => self.consume_multi_line_comment(),
             ('+', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::Plus),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::Plus), String::from(c))
             }
             ('{', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::LBrace),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::LBrace), String::from(c))
             }
             ('}', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::RBrace),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::RBrace), String::from(c))
             }
             ('(', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::LParen),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::LParen), String::from(c))
             }
             (')', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::RParen),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::RParen), String::from(c))
             }
             (':', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::Colon),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::Colon), String::from(c))
             }
             (';', _) => {
                 self.advance();
-                Token {
-                    kind: TokenKind::Op(Operator::Semicolon),
-                    value: String::from(c),
-                }
+                Token::new(TokenKind::Op(Operator::Semicolon), String::from(c))
             }
             ('-', '-') => self.consume_css_variable(),
             ('$', _) => self.consume_variable(),
+            ('#', '{') => {
+                let interpolation = self.consume_interpolation();
+                Token::new(TokenKind::Interpolation, interpolation)
+            }
             ('.', _) => self.consume_class(),
             ('@', 'i') => self.consume_import_or_include(),
             ('@', 'u') => self.consume_use(),
             ('@', 'm') => self.consume_mixin_or_media(),
+            ('@', _) => self.consume_at_keyword(),
             ('&', _) if self.peek() == Some(&' ') || self.peek() == Some(&'.') => {
                 self.consume_nested_class()
             }
             ('&', ':') => self.consume_pseudo_class(),
+            ('&', _) => self.consume_parent_selector(),
             _ if c.is_whitespace() && c != '\n' => self.consume_indentation(),
             _ if c.is_alphabetic() || c == '_' => self.consume_element_or_property(),
             _ => {
                 debug!(
-                    "Unexpected character at position {}: '{}'",
-                    self.position, c
+                    "Unexpected character at {}:{}: '{}'",
+                    self.line, self.column, c
                 );
-                Token {
-                    kind: TokenKind::EOF,
-                    value: String::new(),
-                }
-            } // Handle unexpected characters
+                // Keep going after characters we do not understand (combinators,
+                // attribute selectors, ids, ...) instead of ending the stream
+                self.advance();
+                Token::new(TokenKind::Unknown, String::from(c))
+            }
         }
     }
 
@@ -225,24 +271,32 @@ impl<'a> Lexer<'a> {
     }
 
     fn advance(&mut self) {
+        if let Some(c) = self.current_char {
+            self.position += c.len_utf8();
+            match c {
+                // A '\r' directly followed by '\n' is a single line break
+                '\r' if self.peek() == Some(&'\n') => self.column += 1,
+                '\r' | '\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                _ => self.column += 1,
+            }
+        }
         self.current_char = self.input.next();
-        self.position += 1;
     }
 
     fn consume_indentation(&mut self) -> Token {
         let mut indent_level = 0;
         while let Some(c) = self.current_char {
-            if c == ' ' || c == '\t' {
+            if c.is_whitespace() && c != '\n' && c != '\r' {
                 indent_level += 1;
                 self.advance();
             } else {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Indent(indent_level),
-            value: String::new(),
-        }
+        Token::new(TokenKind::Indent(indent_level), String::new())
     }
 
     fn consume_single_line_comment(&mut self) -> Token {
@@ -256,10 +310,7 @@ impl<'a> Lexer<'a> {
             comment.push(c);
             self.advance();
         }
-        Token {
-            kind: TokenKind::Comment,
-            value: comment,
-        }
+        Token::new(TokenKind::Comment, comment)
     }
 
     fn consume_multi_line_comment(&mut self) -> Token {
@@ -275,27 +326,21 @@ impl<'a> Lexer<'a> {
             comment.push(c);
             self.advance();
         }
-        Token {
-            kind: TokenKind::Comment,
-            value: comment,
-        }
+        Token::new(TokenKind::Comment, comment)
     }
 
     fn consume_variable(&mut self) -> Token {
         let mut variable = String::new();
         self.advance(); // Skip the '$'
         while let Some(c) = self.current_char {
-            if c.is_alphanumeric() || c == '_' {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
                 variable.push(c);
                 self.advance();
             } else {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Variable,
-            value: variable,
-        }
+        Token::new(TokenKind::Variable, variable)
     }
 
     fn consume_class(&mut self) -> Token {
@@ -309,10 +354,7 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Class(false),
-            value: class,
-        }
+        Token::new(TokenKind::Class(false), class)
     }
 
     fn consume_nested_class(&mut self) -> Token {
@@ -330,10 +372,21 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Class(true),
-            value: class,
+        Token::new(TokenKind::Class(true), class)
+    }
+
+    fn consume_parent_selector(&mut self) -> Token {
+        let mut suffix = String::new();
+        self.advance(); // Skip the '&'
+        while let Some(c) = self.current_char {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                suffix.push(c);
+                self.advance();
+            } else {
+                break;
+            }
         }
+        Token::new(TokenKind::Parent, suffix)
     }
 
     fn consume_pseudo_class(&mut self) -> Token {
@@ -348,10 +401,8 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Property(pseudo_class), // Pseudo-classes are treated as properties
-            value: String::from(""),
-        }
+        // Pseudo-classes are treated as properties
+        Token::new(TokenKind::Property(pseudo_class), String::from(""))
     }
 
     fn consume_import_or_include(&mut self) -> Token {
@@ -382,14 +433,12 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        Token {
-            kind: if is_import {
-                TokenKind::Import
-            } else {
-                TokenKind::Include
-            },
-            value: import,
-        }
+        let kind = if is_import {
+            TokenKind::Import
+        } else {
+            TokenKind::Include
+        };
+        Token::new(kind, import)
     }
 
     fn consume_use(&mut self) -> Token {
@@ -416,10 +465,8 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        Token {
-            kind: TokenKind::Include, // Use is treated as an include in this context
-            value: use_statement,
-        }
+        // Use is treated as an include in this context
+        Token::new(TokenKind::Include, use_statement)
     }
 
     fn consume_mixin_or_media(&mut self) -> Token {
@@ -437,57 +484,42 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Mixin,
-            value: mixin,
-        }
+        Token::new(TokenKind::Mixin, mixin)
     }
 
     fn consume_media(&mut self) -> Token {
         while let Some(c) = self.current_char {
-            if c != '\n' {
+            if c != '\n' && c != '{' {
                 self.advance();
             } else {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::Media,
-            value: String::from("media"),
-        }
+        Token::new(TokenKind::Media, String::from("media"))
     }
 
     fn consume_element_or_property(&mut self) -> Token {
         let mut element = String::new();
-        let mut token = Token {
-            kind: TokenKind::EOF,
-            value: String::new(),
-        };
+        let mut token = None;
         while let Some(c) = self.current_char {
-            if self.peek() == Some(&':') {
-                element.push(c);
-                self.advance(); // Skip the character
-                if self.peek() == Some(&' ') {
-                    self.advance(); // Skip the ' '
-                    token = Token {
-                        kind: TokenKind::Property(element.clone()),
-                        value: self.consume_property_value(),
-                    };
-                    break;
-                }
+            if !(c.is_alphanumeric() || c == '-' || c == '_' || c == ' ') {
+                // Also ends at the ':' of pseudo-classes like div:hover
+                token = Some(Token::new(TokenKind::Element, element.clone()));
+                break;
             }
-            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
-                element.push(c);
-                self.advance();
-            } else {
-                token = Token {
-                    kind: TokenKind::Element,
-                    value: element.clone(),
-                };
+            element.push(c);
+            self.advance();
+            if self.current_char == Some(':') && self.peek() == Some(&' ') {
+                self.advance(); // Skip the ':'
+                token = Some(Token::new(
+                    TokenKind::Property(element.clone()),
+                    self.consume_property_value(),
+                ));
                 break;
             }
         }
-        token
+        // An element running up to the end of the input is still an element
+        token.unwrap_or_else(|| Token::new(TokenKind::Element, element))
     }
 
     fn consume_property_value(&mut self) -> String {
@@ -496,8 +528,14 @@ impl<'a> Lexer<'a> {
             if c == ';' {
                 self.advance(); // Skip the ';'
                 break;
+            } else if c == '}' || c == '{' {
+                break; // The last declaration of a block may omit the ';'
             } else if c == ':' || c == ' ' {
                 self.advance();
+            } else if c == '\'' || c == '"' {
+                self.consume_string(&mut value);
+            } else if c == '#' && self.peek() == Some(&'{') {
+                value.push_str(&self.consume_interpolation());
             } else if c.is_alphanumeric()
                 || c == '-'
                 || c == '_'
@@ -513,6 +551,7 @@ impl<'a> Lexer<'a> {
                 || c == '*'
                 || c == '.'
                 || c == '\n'
+                || c == '\r'
                 || c == '\t'
                 || c == '/'
                 || c == '#'
@@ -521,12 +560,100 @@ impl<'a> Lexer<'a> {
                 value.push(c);
                 self.advance();
             } else {
-                panic!("Unexpected character in property value: {}", c);
+                let span = self.current_span();
+                self.report(
+                    span,
+                    format!(
+                        "Unexpected character in property value: '{}'",
+                        c.escape_debug()
+                    ),
+                );
+                self.skip_property_value();
+                break;
             }
         }
         value
     }
 
+    fn consume_string(&mut self, value: &mut String) {
+        let start = self.current_span();
+        let quote = self.current_char;
+        value.extend(quote);
+        self.advance(); // Skip the opening quote
+        while let Some(c) = self.current_char {
+            value.push(c);
+            self.advance();
+            if c == '\\' {
+                value.extend(self.current_char);
+                self.advance(); // Keep escaped characters, including quotes
+            } else if Some(c) == quote {
+                return;
+            } else if c == '\n' {
+                break;
+            }
+        }
+        self.report(start, String::from("Unterminated string in property value"));
+    }
+
+    fn consume_interpolation(&mut self) -> String {
+        let mut interpolation = String::new();
+        while let Some(c) = self.current_char {
+            interpolation.push(c);
+            self.advance();
+            if c == '}' {
+                break;
+            }
+        }
+        interpolation
+    }
+
+    /// Skips the rest of a property value after an error, so lexing resumes
+    /// at the next declaration or the end of the block.
+    fn skip_property_value(&mut self) {
+        while let Some(c) = self.current_char {
+            if c == '}' {
+                break;
+            }
+            self.advance();
+            if c == ';' {
+                break;
+            }
+        }
+    }
+
+    /// Span of the current character.
+    fn current_span(&self) -> Span {
+        let len = self.current_char.map(char::len_utf8).unwrap_or(0);
+        Span {
+            start: self.position,
+            end: self.position + len,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn report(&mut self, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic {
+            file_path: self.file_path.clone(),
+            span,
+            message,
+        });
+    }
+
+    fn consume_at_keyword(&mut self) -> Token {
+        let mut keyword = String::new();
+        self.advance(); // Skip the '@'
+        while let Some(c) = self.current_char {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                keyword.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        Token::new(TokenKind::AtKeyword, keyword)
+    }
+
     fn consume_css_variable(&mut self) -> Token {
         let mut variable = String::new();
         self.advance(); // Skip the first '-'
@@ -539,10 +666,7 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        Token {
-            kind: TokenKind::CssVariable,
-            value: variable,
-        }
+        Token::new(TokenKind::CssVariable, variable)
     }
 }
 
@@ -550,6 +674,11 @@ impl<'a> Lexer<'a> {
 mod tests {
     use super::*;
 
+    /// Drops the span so tests can compare kind and value only.
+    fn unspanned(token: Token) -> Token {
+        Token::new(token.kind, token.value)
+    }
+
     #[test]
     fn test_lexer_initialization() {
         let input = "div { color: $primary; }";
@@ -563,11 +692,8 @@ mod tests {
         let input = ".class-name { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Class(false),
-                value: "class-name".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Class(false), "class-name".to_string())
         );
     }
 
@@ -576,11 +702,8 @@ mod tests {
         let input = "  div { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Indent(2),
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(2), "".to_string())
         );
     }
 
@@ -589,11 +712,8 @@ mod tests {
         let input = "&:hover { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Property("hover".to_string()),
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Property("hover".to_string()), "".to_string())
         );
     }
 
@@ -602,25 +722,16 @@ mod tests {
         let input = "// This is a comment\ndiv { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Comment,
-                value: " This is a comment".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Comment, " This is a comment".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Op(Operator::NewLine),
-                value: "\\n".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::NewLine), "\\n".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Element,
-                value: "div ".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Element, "div ".to_string())
         );
     }
 
@@ -629,25 +740,19 @@ mod tests {
         let input = "/* This is a\nmulti-line comment */\ndiv { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Comment,
-                value: " This is a\nmulti-line comment ".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(
+                TokenKind::Comment,
+                " This is a\nmulti-line comment ".to_string()
+            )
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Op(Operator::NewLine),
-                value: "\\n".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::NewLine), "\\n".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Element,
-                value: "div ".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Element, "div ".to_string())
         );
     }
 
@@ -656,11 +761,8 @@ mod tests {
         let input = "$primary: #ff0000;";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Variable,
-                value: "primary".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Variable, "primary".to_string())
         );
     }
 
@@ -669,11 +771,8 @@ mod tests {
         let input = ".class-name { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Class(false),
-                value: "class-name".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Class(false), "class-name".to_string())
         );
     }
 
@@ -682,11 +781,8 @@ mod tests {
         let input = "&.child { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Class(true),
-                value: "child".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Class(true), "child".to_string())
         );
     }
 
@@ -695,11 +791,8 @@ mod tests {
         let input = "@import 'styles.css';";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Import,
-                value: "import 'styles.css'".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Import, "import 'styles.css'".to_string())
         );
     }
 
@@ -708,47 +801,36 @@ mod tests {
         let input = "div { color: $primary; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Element,
-                value: "div ".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Element, "div ".to_string())
         );
 
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Op(Operator::LBrace),
-                value: "{".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::LBrace), "{".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Indent(1),
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(1), "".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Property("color".to_string()),
-                value: "$primary".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(
+                TokenKind::Property("color".to_string()),
+                "$primary".to_string()
+            )
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Indent(1),
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(1), "".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::EOF,
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::RBrace), "}".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::EOF, "".to_string())
         );
     }
 
@@ -757,46 +839,35 @@ mod tests {
         let input = "div { height: 100%; }";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Element,
-                value: "div ".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Element, "div ".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Op(Operator::LBrace),
-                value: "{".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::LBrace), "{".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Indent(1),
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(1), "".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Property("height".to_string()),
-                value: "100%".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(
+                TokenKind::Property("height".to_string()),
+                "100%".to_string()
+            )
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::Indent(1),
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(1), "".to_string())
         );
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::EOF,
-                value: "".to_string()
-            }
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::RBrace), "}".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::EOF, "".to_string())
         );
     }
 
@@ -805,11 +876,254 @@ mod tests {
         let input = "--primary-color: var(--color) !important;";
         let mut lexer = Lexer::new(input);
         assert_eq!(
-            lexer.next_token(),
-            Token {
-                kind: TokenKind::CssVariable,
-                value: "primary-color".to_string()
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::CssVariable, "primary-color".to_string())
+        );
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = ".a {\n  color: red;\n}\n";
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        assert_eq!(
+            tokens[0].span,
+            Span {
+                start: 0,
+                end: 2,
+                line: 1,
+                column: 1
             }
         );
+        let property = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Property("color".to_string()))
+            .unwrap();
+        assert_eq!(property.span.line, 2);
+        assert_eq!(property.span.column, 3);
+        assert_eq!(property.span.start, 7);
+        assert_eq!(
+            &input[property.span.start..property.span.end],
+            "color: red;"
+        );
+    }
+
+    #[test]
+    fn test_token_spans_with_crlf() {
+        let input = ".a {\r\n}\r\n.b {\r\n}\r\n";
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        let b = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Class(false) && t.value == "b")
+            .unwrap();
+        assert_eq!(b.span.line, 3);
+        assert_eq!(b.span.column, 1);
+        assert_eq!(&input[b.span.start..b.span.end], ".b");
+    }
+
+    #[test]
+    fn test_token_spans_with_multibyte_characters() {
+        let input = "/* größe → ✓ */\n.ü-klasse {\n}\n";
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        assert_eq!(tokens[0].span.end, "/* größe → ✓ */".len());
+        let class = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Class(false))
+            .unwrap();
+        assert_eq!(class.value, "ü-klasse");
+        assert_eq!(class.span.line, 2);
+        assert_eq!(class.span.column, 1);
+        assert_eq!(&input[class.span.start..class.span.end], ".ü-klasse");
+        let brace = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Op(Operator::LBrace))
+            .unwrap();
+        assert_eq!(brace.span.column, 11);
+    }
+
+    fn classes(input: &str) -> Vec<String> {
+        Lexer::new(input)
+            .filter(|t| matches!(t.kind, TokenKind::Class(_)))
+            .map(|t| t.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_character_token() {
+        let input = "> .child";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Unknown, ">".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(1), "".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Class(false), "child".to_string())
+        );
+    }
+
+    #[test]
+    fn test_classes_after_combinators() {
+        let input = ".list > .item {\n}\n.a ~ .b {\n}\n.c + .d {\n}\n.last {\n}\n";
+        assert_eq!(
+            classes(input),
+            vec!["list", "item", "a", "b", "c", "d", "last"]
+        );
+    }
+
+    #[test]
+    fn test_classes_after_selector_list() {
+        let input = ".first,\n.second, .third {\n  color: red;\n}\n";
+        assert_eq!(classes(input), vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_classes_after_attribute_id_and_universal_selectors() {
+        let input = "[data-open] .menu {\n}\n#root .app {\n}\n* {\n}\n.after {\n}\n";
+        assert_eq!(classes(input), vec!["menu", "app", "after"]);
+    }
+
+    #[test]
+    fn test_last_character_is_tokenized() {
+        let tokens = Lexer::new(".a {}").collect::<Vec<_>>();
+        assert_eq!(
+            tokens.last().map(|t| &t.kind),
+            Some(&TokenKind::Op(Operator::RBrace))
+        );
+        assert_eq!(classes(".a, .b"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_quoted_property_values() {
+        let input =
+            ".icon::after {\n  content: '→';\n}\n.grid {\n  grid-template-areas: 'a b';\n}\n";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.by_ref().collect::<Vec<_>>();
+        assert!(lexer.diagnostics().is_empty());
+        let values = tokens
+            .iter()
+            .filter(|t| matches!(t.kind, TokenKind::Property(_)))
+            .map(|t| t.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["'→'", "'a b'"]);
+        assert_eq!(classes(input), vec!["icon", "grid"]);
+    }
+
+    #[test]
+    fn test_invalid_property_value_is_reported() {
+        let input = ".a {\n  filter: alpha(opacity=50);\n}\n.b {\n  color: red\n}\n";
+        let mut lexer = Lexer::new(input).with_file_path("src/a.scss");
+        let tokens = lexer.by_ref().collect::<Vec<_>>();
+        assert_eq!(
+            lexer
+                .diagnostics()
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>(),
+            vec!["src/a.scss:2:24: Unexpected character in property value: '='"]
+        );
+        assert!(tokens
+            .iter()
+            .any(|t| t.kind == TokenKind::Class(false) && t.value == "b"));
+    }
+
+    #[test]
+    fn test_crlf_property_values_are_valid() {
+        let input = ".a {\r\n  margin: 0\r\n}\r\n.b {\r\n  font: 12px\r\n    serif;\r\n  x: a\u{7}\r\n}\r\n";
+        let mut lexer = Lexer::new(input).with_file_path("src/a.scss");
+        lexer.by_ref().for_each(drop);
+        assert_eq!(
+            lexer
+                .diagnostics()
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>(),
+            vec!["src/a.scss:7:7: Unexpected character in property value: '\\u{7}'"]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string_is_reported() {
+        let input = ".a {\n  content: 'oops;\n}\n.b {\n}\n";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.by_ref().collect::<Vec<_>>();
+        assert_eq!(lexer.diagnostics().len(), 1);
+        assert_eq!(lexer.diagnostics()[0].span.line, 2);
+        assert_eq!(lexer.diagnostics()[0].span.column, 12);
+        assert!(tokens
+            .iter()
+            .any(|t| t.kind == TokenKind::Class(false) && t.value == "b"));
+    }
+
+    #[test]
+    fn test_at_keyword_token() {
+        let input = "@keyframes spin {";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::AtKeyword, "keyframes".to_string())
+        );
+    }
+
+    #[test]
+    fn test_interpolation_token() {
+        let input = ".icon-#{$name} {";
+        let mut lexer = Lexer::new(input);
+        lexer.next_token();
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Interpolation, "#{$name}".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Indent(1), "".to_string())
+        );
+    }
+
+    #[test]
+    fn test_media_keeps_block() {
+        let kinds = Lexer::new("@media (max-width: 600px) {\n}")
+            .map(|t| t.kind)
+            .filter(|k| !matches!(k, TokenKind::Op(Operator::NewLine)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Media,
+                TokenKind::Op(Operator::LBrace),
+                TokenKind::Op(Operator::RBrace)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_element_with_pseudo_class() {
+        let input = "li:first-child {";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Element, "li".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Op(Operator::Colon), ":".to_string())
+        );
+        assert_eq!(
+            unspanned(lexer.next_token()),
+            Token::new(TokenKind::Element, "first-child ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parent_selector_suffix_tokens() {
+        let input = "&__title, &--active, &-icon {";
+        let tokens = Lexer::new(input)
+            .filter(|t| t.kind == TokenKind::Parent)
+            .map(|t| t.value)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["__title", "--active", "-icon"]);
     }
 }
@@ -1,5 +1,340 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use glob::{MatchOptions, Pattern};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use walkdir::{DirEntry, WalkDir};
-use log::info;
+use log::{info, warn};
+
+// `*` stops at `/`, `**` crosses directories
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Stylesheets that get declarations, unless changed with `--modules-pattern`.
+pub const DEFAULT_MODULES_PATTERN: &str = "**/*.module.{scss,css}";
+
+/// Globs from `--modules-pattern`, `--include` and `--exclude`, matched
+/// against paths relative to the root with `/` separators, like
+/// `src/**/*.module.scss`. Sass partials like `_variables.scss` never match.
+/// Files ignored by `.gitignore`, `.ignore` or the global git excludes are
+/// skipped as well, unless disabled with `--no-ignore`. The ignore files are
+/// read once and shared by the clones of a filter.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    modules: Vec<Pattern>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    ignore_files: Option<Arc<Mutex<IgnoreFiles>>>,
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        Ok(Filter {
+            modules: parse_patterns(&[DEFAULT_MODULES_PATTERN.to_string()])?,
+            include: parse_patterns(include)?,
+            exclude: parse_patterns(exclude)?,
+            ignore_files: Some(Arc::default()),
+        })
+    }
+
+    /// Replaces the pattern of the stylesheets that are CSS modules.
+    pub fn with_modules_pattern(mut self, pattern: &str) -> Result<Self, String> {
+        self.modules = parse_patterns(&[pattern.to_string()])?;
+        Ok(self)
+    }
+
+    /// Whether ignore files are respected.
+    pub fn with_ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files.then(Arc::default);
+        self
+    }
+
+    /// Forgets the ignore files read so far, after one of them changed.
+    pub fn reload_ignore_files(&self) {
+        if let Some(ignore_files) = &self.ignore_files {
+            *ignore_files.lock().unwrap() = IgnoreFiles::default();
+        }
+    }
+
+    fn is_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        self.ignore_files
+            .as_ref()
+            .is_some_and(|ignore_files| ignore_files.lock().unwrap().is_ignored(root, path, is_dir))
+    }
+
+    /// Whether a file or directory is skipped, a directory with everything
+    /// below it.
+    fn excludes(&self, relative_path: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches_with(relative_path, MATCH_OPTIONS))
+    }
+
+    /// Whether a file is a CSS module that is wanted, every module without
+    /// `--include`.
+    fn selects(&self, relative_path: &str) -> bool {
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches_with(relative_path, MATCH_OPTIONS))
+        };
+        !is_partial(relative_path)
+            && matches(&self.modules)
+            && (self.include.is_empty() || matches(&self.include))
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::new(&[], &[]).unwrap()
+    }
+}
+
+/// The ignore files ripgrep respects: `.ignore`, `.gitignore` and
+/// `.git/info/exclude` of the directories above a path up to the enclosing
+/// git repository, or the root outside of one, the deepest one deciding, then
+/// the global git excludes. Each directory is read once.
+#[derive(Debug, Default)]
+struct IgnoreFiles {
+    global: Option<Gitignore>,
+    // The deepest directory whose ignore files apply below each root
+    tops: HashMap<PathBuf, PathBuf>,
+    // The matchers of a directory and whether it is a repository
+    directories: HashMap<PathBuf, (Vec<Gitignore>, bool)>,
+}
+
+impl IgnoreFiles {
+    fn global(&mut self) -> &Gitignore {
+        self.global.get_or_insert_with(|| {
+            let (global, error) = Gitignore::global();
+            if let Some(e) = error {
+                warn!("Could not read the global git excludes: {}", e);
+            }
+            global
+        })
+    }
+
+    fn top(&mut self, root: &Path) -> &Path {
+        self.tops.entry(root.to_path_buf()).or_insert_with(|| {
+            root.ancestors()
+                .find(|ancestor| is_repository(ancestor))
+                .unwrap_or(root)
+                .to_path_buf()
+        })
+    }
+
+    fn read(directory: &Path) -> Vec<Gitignore> {
+        [".ignore", ".gitignore", ".git/info/exclude"]
+            .iter()
+            .map(|name| directory.join(name))
+            .filter(|file| file.is_file())
+            .filter_map(|file| {
+                let mut builder = GitignoreBuilder::new(directory);
+                if let Some(e) = builder.add(&file) {
+                    warn!("{}", e);
+                }
+                builder.build().ok()
+            })
+            .collect()
+    }
+
+    fn is_ignored(&mut self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let top = self.top(root).to_path_buf();
+        for directory in path.ancestors().skip(1) {
+            if !directory.starts_with(&top) {
+                break;
+            }
+            let (matchers, repository) = self
+                .directories
+                .entry(directory.to_path_buf())
+                .or_insert_with(|| (Self::read(directory), is_repository(directory)));
+            for matcher in matchers.iter() {
+                let matched = matcher.matched_path_or_any_parents(path, is_dir);
+                if !matched.is_none() {
+                    return matched.is_ignore();
+                }
+            }
+            // A nested repository doesn't use the ignore files around it
+            if *repository {
+                break;
+            }
+        }
+        // Global patterns are relative to the root rather than the working directory
+        match path.strip_prefix(root) {
+            Ok(relative) => self
+                .global()
+                .matched_path_or_any_parents(relative, is_dir)
+                .is_ignore(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Whether the path is one of the files `IgnoreFiles` reads.
+pub fn is_ignore_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == ".ignore" || name == ".gitignore")
+        || path.ends_with(".git/info/exclude")
+}
+
+fn is_repository(directory: &Path) -> bool {
+    directory.join(".git").exists()
+}
+
+fn parse_patterns(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns
+        .iter()
+        .flat_map(|pattern| {
+            expand_braces(pattern).into_iter().map(move |expanded| {
+                Pattern::new(&expanded).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))
+            })
+        })
+        .collect()
+}
+
+/// Expands `{a,b}` alternatives, which `glob` doesn't support, like
+/// `*.{scss,css}` to `*.scss` and `*.css`. Alternatives can't be nested.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let Some(close) = pattern[open..].find('}').map(|index| open + index) else {
+        return vec![pattern.to_string()];
+    };
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| {
+            expand_braces(&format!(
+                "{}{}{}",
+                &pattern[..open],
+                alternative,
+                &pattern[close + 1..]
+            ))
+        })
+        .collect()
+}
+
+/// Sass partials like `_variables.scss` are only imported by other stylesheets.
+fn is_partial(relative_path: &str) -> bool {
+    relative_path
+        .rsplit('/')
+        .next()
+        .is_some_and(|name| name.starts_with('_'))
+}
+
+/// `path` relative to `root` with `/` separators, `None` outside of it.
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Directories and stylesheets given on the command line, canonicalized.
+/// Directories inside another one and files given twice are dropped. When
+/// files are given the directories are not searched, they only tell what the
+/// files are relative to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Inputs {
+    pub roots: Vec<PathBuf>,
+    pub files: Vec<PathBuf>,
+}
+
+impl Inputs {
+    /// Sorts the paths into directories and files, the ones that can't be
+    /// resolved are returned as errors.
+    pub fn resolve(paths: &[PathBuf]) -> (Self, Vec<String>) {
+        let mut roots = BTreeSet::new();
+        let mut files = BTreeSet::new();
+        let mut errors = Vec::new();
+        for path in paths {
+            match path.canonicalize() {
+                Ok(path) if path.is_dir() => {
+                    roots.insert(path);
+                }
+                Ok(path) => {
+                    files.insert(path);
+                }
+                Err(e) => errors.push(format!("Could not read {}: {}", path.display(), e)),
+            }
+        }
+
+        // Directories come before their subdirectories in a `BTreeSet`
+        let mut inputs = Inputs::default();
+        for root in roots {
+            if !inputs.roots.iter().any(|parent| root.starts_with(parent)) {
+                inputs.roots.push(root);
+            }
+        }
+        inputs.files = files.into_iter().collect();
+        (inputs, errors)
+    }
+
+    /// Adds stylesheets from `--stdin` or `--files-from`. Entries that are
+    /// missing or not files are returned as warnings, lists from
+    /// `git diff --name-only` contain deleted files as well.
+    pub fn add_files(&mut self, paths: &[PathBuf]) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for path in paths {
+            match path.canonicalize() {
+                Ok(file) if file.is_file() => self.files.push(file),
+                Ok(_) => warnings.push(format!("Skipping {}, it is not a file", path.display())),
+                Err(e) => warnings.push(format!("Skipping {}: {}", path.display(), e)),
+            }
+        }
+        self.files.sort();
+        self.files.dedup();
+        warnings
+    }
+
+    /// The directory paths are relative to, for `--out-dir` and templates:
+    /// the common parent of the directories, or `current_dir` when only files
+    /// are given. Files get the same paths as when their directory is
+    /// searched.
+    pub fn base(&self, current_dir: &Path) -> PathBuf {
+        let mut base = self.roots.first().map_or(current_dir, |root| root).to_path_buf();
+        for root in &self.roots {
+            while !root.starts_with(&base) {
+                base.pop();
+            }
+        }
+        base
+    }
+
+    /// The directory the patterns of a file are relative to, the deepest
+    /// directory containing it or `base` when only files are given. Files
+    /// outside of them have none.
+    pub fn root_of<'a>(&'a self, file: &Path, base: &'a Path) -> Option<&'a Path> {
+        if self.roots.is_empty() {
+            return file.starts_with(base).then_some(base);
+        }
+        self.roots
+            .iter()
+            .filter(|root| file.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map(|root| root.as_path())
+    }
+}
+
+/// Paths one per line, or separated by NUL bytes like the output of
+/// `git diff -z --name-only`. Empty entries are left out.
+pub fn parse_path_list(content: &[u8]) -> Vec<PathBuf> {
+    let content = String::from_utf8_lossy(content);
+    let separator = if content.contains('\0') { '\0' } else { '\n' };
+    content
+        .split(separator)
+        .map(|entry| entry.strip_suffix('\r').unwrap_or(entry))
+        .filter(|entry| !entry.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
 
 fn matches_file_type(entry: &DirEntry, file_type: &str) -> bool {
     entry
@@ -9,47 +344,517 @@ fn matches_file_type(entry: &DirEntry, file_type: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn is_node_modules(entry: &DirEntry) -> bool {
-    entry
-        .path()
-        .to_str()
-        .map(|e| e.contains("node_modules"))
-        .unwrap_or(false)
+fn is_node_modules(name: &str) -> bool {
+    name == "node_modules"
 }
 
-fn is_yalc(entry: &DirEntry) -> bool {
-    entry
-        .path()
-        .to_str()
-        .map(|e| e.contains(".yalc"))
-        .unwrap_or(false)
+fn is_yalc(name: &str) -> bool {
+    name == ".yalc"
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .path()
-        .to_str()
-        .map(|s| s.starts_with("."))
-        .unwrap_or(false)
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
 }
 
-fn is_dist(entry: &DirEntry) -> bool {
-    entry
-        .path()
-        .to_str()
-        .map(|s| s.contains("dist"))
+fn is_dist(name: &str) -> bool {
+    name == "dist"
+}
+
+fn is_excluded_name(name: &OsStr) -> bool {
+    name.to_str()
+        .map(|name| is_node_modules(name) || is_hidden(name) || is_yalc(name) || is_dist(name))
         .unwrap_or(false)
 }
 
-pub fn get_scss_files(path: &str) -> impl Iterator<Item = walkdir::DirEntry> {
-    info!("Searching for files in: {}", path);
-    WalkDir::new(String::from(path))
+/// Whether the path is skipped when searching for stylesheets: a file or
+/// directory below `root` is hidden or named `node_modules`, `.yalc` or
+/// `dist`. Only whole names count, `src/distribution` is searched, and
+/// directories above `root` don't matter.
+pub fn is_excluded(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root).is_ok_and(|relative| {
+        relative.components().any(|component| match component {
+            Component::Normal(name) => is_excluded_name(name),
+            _ => false,
+        })
+    })
+}
+
+/// Whether the path or one of its directories below `root` matches an
+/// `--exclude` pattern or is ignored by an ignore file.
+pub fn is_filtered_out(root: &Path, path: &Path, filter: &Filter) -> bool {
+    if path == root || !path.starts_with(root) {
+        return false;
+    }
+    let excluded = path
+        .ancestors()
+        .take_while(|ancestor| *ancestor != root)
+        .filter_map(|ancestor| relative_path(root, ancestor))
+        .any(|relative| filter.excludes(&relative));
+    excluded || filter.is_ignored(root, path, path.is_dir())
+}
+
+/// Whether the path is a stylesheet `get_scss_files` would return.
+pub fn is_scss_file(root: &Path, path: &Path, filter: &Filter) -> bool {
+    !is_excluded(root, path)
+        && relative_path(root, path).is_some_and(|relative| filter.selects(&relative))
+        && !is_filtered_out(root, path, filter)
+}
+
+/// Walks `start`, never entering directories excluded relative to `root`,
+/// and with `filtered` the ones matching an `--exclude` pattern or ignored by
+/// an ignore file.
+fn walk<'a>(
+    root: &'a Path,
+    start: &Path,
+    filter: &'a Filter,
+    filtered: bool,
+) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+    WalkDir::new(start)
         .follow_links(false)
         .into_iter()
+        .filter_entry(move |e| {
+            if e.path() == root {
+                return true;
+            }
+            let filtered_out = filtered
+                && (relative_path(root, e.path()).is_some_and(|relative| filter.excludes(&relative))
+                    || filter.is_ignored(root, e.path(), e.file_type().is_dir()));
+            !is_excluded(root, e.path()) && !filtered_out
+        })
         .filter_map(Result::ok)
-        .filter(|e| !is_node_modules(e))
-        .filter(|e| !is_hidden(e))
-        .filter(|e| !is_yalc(e))
-        .filter(|e| !is_dist(e))
-        .filter(|e| matches_file_type(e, "scss"))
+}
+
+pub fn get_scss_files<'a>(
+    path: &'a str,
+    filter: &'a Filter,
+) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+    info!("Searching for files in: {}", path);
+    get_scss_files_in(Path::new(path), Path::new(path), filter)
+}
+
+/// Stylesheets in `directory`, with the patterns evaluated relative to `root`.
+pub fn get_scss_files_in<'a>(
+    root: &'a Path,
+    directory: &Path,
+    filter: &'a Filter,
+) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+    walk(root, directory, filter, true).filter(move |e| {
+        e.file_type().is_file()
+            && relative_path(root, e.path()).is_some_and(|relative| filter.selects(&relative))
+    })
+}
+
+/// Declaration files of stylesheets, like `button.module.scss.d.ts`, to find
+/// the ones left behind by deleted or renamed stylesheets. Excluded
+/// directories are not searched, but ignored ones are: generated declarations
+/// and `--out-dir` are often ignored themselves.
+pub fn get_declaration_files<'a>(
+    path: &'a str,
+    filter: &'a Filter,
+) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+    let root = Path::new(path);
+    walk(root, root, filter, false).filter(move |e| {
+        matches_file_type(e, "d.ts")
+            && relative_path(root, e.path()).is_some_and(|relative| {
+                filter.selects(relative.trim_end_matches(".d.ts"))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::generator::{Generator, GeneratorOptions};
+    use std::slice;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tree(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, ".a {}\n").unwrap();
+        }
+        dir
+    }
+
+    fn found(root: &Path, filter: &Filter) -> Vec<String> {
+        let mut files = get_scss_files(root.to_str().unwrap(), filter)
+            .map(|e| relative_path(root, e.path()).unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn test_excluded_names_are_whole_components() {
+        let dir = tree(&[
+            "src/card.module.scss",
+            "src/distribution/card.module.scss",
+            "src/dist/card.module.scss",
+            "dist/card.module.scss",
+            "node_modules/lib/card.module.scss",
+            "packages/ui/node_modules/lib/card.module.scss",
+            "node_modules_backup/card.module.scss",
+            ".yalc/lib/card.module.scss",
+            "yalc/card.module.scss",
+            ".storybook/preview.scss",
+            "src/.hidden.module.scss",
+        ]);
+        assert_eq!(
+            found(dir.path(), &Filter::default()),
+            vec![
+                "node_modules_backup/card.module.scss",
+                "src/card.module.scss",
+                "src/distribution/card.module.scss",
+                "yalc/card.module.scss",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directories_above_the_root_dont_exclude() {
+        // The root of a temporary directory is hidden itself, like `/tmp/.tmpXYZ`
+        let dir = tree(&[
+            "home/dister/app/src/card.module.scss",
+            ".config/node_modules/app/card.module.scss",
+        ]);
+        for root in ["home/dister/app", ".config/node_modules/app"] {
+            let root = dir.path().join(root);
+            assert_eq!(found(&root, &Filter::default()).len(), 1, "{}", root.display());
+        }
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let root = Path::new("/home/dister/.app");
+        assert!(!is_excluded(root, Path::new("/home/dister/.app/src/card.module.scss")));
+        assert!(!is_excluded(root, Path::new("/home/dister/.app/src/distribution/card.module.scss")));
+        assert!(!is_excluded(root, Path::new("/home/dister/.app/src/my.dist.module.scss")));
+        assert!(is_excluded(root, Path::new("/home/dister/.app/dist/card.module.scss")));
+        assert!(is_excluded(root, Path::new("/home/dister/.app/src/.cache/card.module.scss")));
+        assert!(is_excluded(root, Path::new("/home/dister/.app/node_modules")));
+        assert!(!is_excluded(root, root));
+        assert!(!is_excluded(root, Path::new("/elsewhere/dist/card.module.scss")));
+    }
+
+    #[test]
+    fn test_excluded_directories_are_pruned() {
+        let dir = tree(&["src/card.module.scss", "node_modules/lib/deep/card.module.scss"]);
+        let root = dir.path();
+        let visited = walk(root, root, &Filter::default(), true)
+            .map(|e| e.into_path())
+            .collect::<Vec<_>>();
+        assert!(visited.contains(&root.join("src/card.module.scss")));
+        assert!(visited.iter().all(|path| !path.starts_with(root.join("node_modules"))));
+    }
+
+    #[test]
+    fn test_include_and_exclude_patterns() {
+        let dir = tree(&[
+            "src/app.module.scss",
+            "src/legacy/old.module.scss",
+            "src/components/legacy/card.module.scss",
+            "src/theme.module.css",
+            "stories/button.module.scss",
+        ]);
+        let root = dir.path();
+        assert_eq!(found(root, &Filter::default()).len(), 5);
+
+        let filter = Filter::new(&patterns(&["src/**/*.module.scss"]), &[]).unwrap();
+        assert_eq!(
+            found(root, &filter),
+            vec![
+                "src/app.module.scss",
+                "src/components/legacy/card.module.scss",
+                "src/legacy/old.module.scss",
+            ]
+        );
+
+        // Patterns are anchored at the root unless they start with `**`
+        let filter = Filter::new(&[], &patterns(&["src/legacy", "stories"])).unwrap();
+        assert_eq!(
+            found(root, &filter),
+            vec![
+                "src/app.module.scss",
+                "src/components/legacy/card.module.scss",
+                "src/theme.module.css",
+            ]
+        );
+        let filter = Filter::new(&patterns(&["**/*.module.scss"]), &patterns(&["**/legacy"])).unwrap();
+        assert_eq!(
+            found(root, &filter),
+            vec!["src/app.module.scss", "stories/button.module.scss"]
+        );
+    }
+
+    #[test]
+    fn test_excluded_directories_are_not_entered() {
+        let dir = tree(&["src/app.module.scss", "generated/deep/a.module.scss"]);
+        let root = dir.path();
+        let filter = Filter::new(&[], &patterns(&["generated"])).unwrap();
+        let visited = walk(root, root, &filter, true)
+            .map(|e| e.into_path())
+            .collect::<Vec<PathBuf>>();
+        assert!(visited.iter().all(|path| !path.starts_with(root.join("generated"))));
+    }
+
+    #[test]
+    fn test_is_scss_file_with_filter() {
+        let root = Path::new("/app");
+        let filter = Filter::new(&patterns(&["src/**"]), &patterns(&["src/legacy"])).unwrap();
+        assert!(is_scss_file(root, Path::new("/app/src/a.module.scss"), &filter));
+        assert!(!is_scss_file(root, Path::new("/app/src/legacy/b/a.module.scss"), &filter));
+        assert!(!is_scss_file(root, Path::new("/app/stories/a.module.scss"), &filter));
+        assert!(is_scss_file(root, Path::new("/app/src/a.module.css"), &filter));
+        assert!(!is_scss_file(root, Path::new("/app/src/a.scss"), &filter));
+        assert!(!is_scss_file(root, Path::new("/other/src/a.module.scss"), &filter));
+    }
+
+    #[test]
+    fn test_ignore_files() {
+        let dir = tree(&[
+            "src/app.module.scss",
+            "src/theme.generated.module.scss",
+            "src/keep.generated.module.scss",
+            "src/stories/storybook-static/button.module.scss",
+            "coverage/report.module.scss",
+            "packages/ui/out/page.module.scss",
+            "packages/ui/card.module.scss",
+        ]);
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "coverage/\n*.generated.module.scss\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "!keep.generated.module.scss\n").unwrap();
+        fs::write(root.join("src/.ignore"), "storybook-static\n").unwrap();
+        fs::write(root.join("packages/ui/.gitignore"), "out\n").unwrap();
+
+        assert_eq!(
+            found(root, &Filter::default()),
+            vec![
+                "packages/ui/card.module.scss",
+                "src/app.module.scss",
+                "src/keep.generated.module.scss",
+            ]
+        );
+        assert_eq!(found(root, &Filter::default().with_ignore_files(false)).len(), 7);
+
+        // Single paths, like the ones of file events
+        let filter = Filter::default();
+        assert!(is_filtered_out(root, &root.join("coverage/report.module.scss"), &filter));
+        assert!(is_filtered_out(root, &root.join("coverage/deleted.module.scss"), &filter));
+        assert!(is_filtered_out(root, &root.join("src/stories/storybook-static"), &filter));
+        assert!(!is_filtered_out(root, &root.join("src/keep.generated.module.scss"), &filter));
+        assert!(!is_scss_file(root, &root.join("src/theme.generated.module.scss"), &filter));
+        assert!(is_scss_file(root, &root.join("src/app.module.scss"), &filter));
+    }
+
+    #[test]
+    fn test_ignore_files_above_the_repository_do_not_apply() {
+        let dir = tree(&["repo/app/a.module.scss", "repo/app/b.module.scss", "other/c.module.scss"]);
+        fs::write(dir.path().join(".gitignore"), "*.module.scss\n").unwrap();
+        fs::create_dir(dir.path().join("repo/.git")).unwrap();
+        fs::write(dir.path().join("repo/.gitignore"), "b.module.scss\n").unwrap();
+
+        // The repository's ignore files apply to a root inside of it
+        let filter = Filter::default();
+        assert_eq!(found(&dir.path().join("repo/app"), &filter), vec!["a.module.scss"]);
+        // Outside of a repository only the root's ones do
+        assert_eq!(found(&dir.path().join("other"), &filter), vec!["c.module.scss"]);
+        assert!(!is_filtered_out(&dir.path().join("other"), &dir.path().join("other/c.module.scss"), &filter));
+
+        fs::write(dir.path().join("repo/.gitignore"), "").unwrap();
+        filter.reload_ignore_files();
+        assert_eq!(found(&dir.path().join("repo/app"), &filter).len(), 2);
+    }
+
+    #[test]
+    fn test_ignored_declarations_are_found() {
+        let dir = tree(&["src/a.module.scss.d.ts", "coverage/b.module.scss.d.ts"]);
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "*.scss.d.ts\ncoverage\n").unwrap();
+        let filter = Filter::default();
+        let mut declarations = get_declaration_files(root.to_str().unwrap(), &filter)
+            .map(|e| e.into_path())
+            .collect::<Vec<_>>();
+        declarations.sort();
+        assert_eq!(
+            declarations,
+            vec![root.join("coverage/b.module.scss.d.ts"), root.join("src/a.module.scss.d.ts")]
+        );
+    }
+
+    #[test]
+    fn test_declarations_in_an_ignored_out_dir_are_found() {
+        let dir = tree(&["src/c/a.module.scss", "types/c/a.module.scss.d.ts", "types/c/b.module.scss.d.ts"]);
+        let root = dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "types/\n").unwrap();
+        let mut declarations = get_declaration_files(root.join("types").to_str().unwrap(), &Filter::default())
+            .map(|e| relative_path(root, e.path()).unwrap())
+            .collect::<Vec<_>>();
+        declarations.sort();
+        assert_eq!(declarations, vec!["types/c/a.module.scss.d.ts", "types/c/b.module.scss.d.ts"]);
+    }
+
+    #[test]
+    fn test_modules_pattern_and_partials() {
+        let dir = tree(&[
+            "src/card.module.scss",
+            "src/card.module.css",
+            "src/_card.module.scss",
+            "src/global.scss",
+            "src/_variables.scss",
+            "src/layout.sass",
+        ]);
+        let root = dir.path();
+        assert_eq!(
+            found(root, &Filter::default()),
+            vec!["src/card.module.css", "src/card.module.scss"]
+        );
+        let filter = Filter::default().with_modules_pattern("src/*.{scss,sass}").unwrap();
+        assert_eq!(
+            found(root, &filter),
+            vec!["src/card.module.scss", "src/global.scss", "src/layout.sass"]
+        );
+    }
+
+    #[test]
+    fn test_declarations_of_modules() {
+        let dir = tree(&[
+            "src/card.module.scss.d.ts",
+            "src/card.module.css.d.ts",
+            "src/global.scss.d.ts",
+            "src/env.d.ts",
+        ]);
+        let root = dir.path();
+        let mut declarations = get_declaration_files(root.to_str().unwrap(), &Filter::default())
+            .map(|e| relative_path(root, e.path()).unwrap())
+            .collect::<Vec<_>>();
+        declarations.sort();
+        assert_eq!(declarations, vec!["src/card.module.css.d.ts", "src/card.module.scss.d.ts"]);
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("**/*.scss"), vec!["**/*.scss"]);
+        assert_eq!(
+            expand_braces("{src,lib}/*.{scss,css}"),
+            vec!["src/*.scss", "src/*.css", "lib/*.scss", "lib/*.css"]
+        );
+        assert_eq!(expand_braces("src/{a"), vec!["src/{a"]);
+    }
+
+    #[test]
+    fn test_resolve_inputs() {
+        let dir = tree(&[
+            "packages/a/src/card.module.scss",
+            "packages/b/src/card.module.scss",
+            "packages/b/src/button.module.scss",
+            "packages/c/card.module.scss",
+        ]);
+        let root = dir.path().canonicalize().unwrap();
+        let (inputs, errors) = Inputs::resolve(&[
+            root.join("packages/b/src"),
+            root.join("packages/a"),
+            root.join("packages/a/src"),
+            root.join("packages/b/src/card.module.scss"),
+            root.join("packages/c/card.module.scss"),
+            root.join("packages/c/../c/card.module.scss"),
+            root.join("packages/c/missing.module.scss"),
+        ]);
+        assert_eq!(
+            inputs,
+            Inputs {
+                roots: vec![root.join("packages/a"), root.join("packages/b/src")],
+                files: vec![
+                    root.join("packages/b/src/card.module.scss"),
+                    root.join("packages/c/card.module.scss"),
+                ],
+            }
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("Could not read "), "{}", errors[0]);
+        assert!(errors[0].contains("missing.module.scss"), "{}", errors[0]);
+
+        assert_eq!(inputs.base(Path::new("/elsewhere")), root.join("packages"));
+        let (inputs, _) = Inputs::resolve(&[root.join("packages/a/src"), root.join("packages/b/src")]);
+        assert_eq!(inputs.base(Path::new("/elsewhere")), root.join("packages"));
+        let (inputs, _) = Inputs::resolve(&[root.join("packages/a/src")]);
+        assert_eq!(inputs.base(Path::new("/elsewhere")), root.join("packages/a/src"));
+        let (inputs, _) = Inputs::resolve(&[root.join("packages/c/card.module.scss")]);
+        assert_eq!(inputs.base(&root), root);
+        assert_eq!(inputs.base(&root.join("packages/a")), root.join("packages/a"));
+    }
+
+    #[test]
+    fn test_files_are_relative_to_the_directory_they_are_searched_in() {
+        let dir = tree(&["src/c/a.module.scss"]);
+        let root = dir.path().canonicalize().unwrap();
+        let file = root.join("src/c/a.module.scss");
+        let declaration_path = |paths: &[PathBuf]| {
+            let (inputs, _) = Inputs::resolve(paths);
+            let base = inputs.base(&root);
+            assert_eq!(inputs.root_of(&file, &base), Some(root.join("src").as_path()));
+            Generator::new(GeneratorOptions {
+                out_dir: Some(root.join("types")),
+                ..GeneratorOptions::default()
+            })
+            .with_root(&base)
+            .declaration_path_for(&file)
+        };
+
+        // `-p src --out-dir types` and `-p src --out-dir types src/c/a.module.scss`
+        let scanned = declaration_path(&[root.join("src")]);
+        assert_eq!(scanned, root.join("types/c/a.module.scss.d.ts"));
+        assert_eq!(declaration_path(&[root.join("src"), file.clone()]), scanned);
+
+        // Only files are relative to the working directory, like `-p .`
+        let (inputs, _) = Inputs::resolve(slice::from_ref(&file));
+        assert_eq!(inputs.root_of(&file, &root.join("src")), Some(root.join("src").as_path()));
+        assert_eq!(inputs.root_of(&file, &root.join("lib")), None);
+    }
+
+    #[test]
+    fn test_parse_path_list() {
+        assert_eq!(
+            parse_path_list(b"src/a.module.scss\r\n\nsrc/b c.module.scss\n"),
+            vec![PathBuf::from("src/a.module.scss"), PathBuf::from("src/b c.module.scss")]
+        );
+        assert_eq!(
+            parse_path_list(b"src/a\nb.module.scss\0src/c.module.scss\0"),
+            vec![PathBuf::from("src/a\nb.module.scss"), PathBuf::from("src/c.module.scss")]
+        );
+        assert!(parse_path_list(b"").is_empty());
+    }
+
+    #[test]
+    fn test_add_listed_files() {
+        let dir = tree(&["src/a.module.scss", "lib/b.module.scss"]);
+        let root = dir.path().canonicalize().unwrap();
+        let (mut inputs, _) = Inputs::resolve(&[root.join("src"), root.join("lib/b.module.scss")]);
+        let warnings = inputs.add_files(&[
+            root.join("lib/b.module.scss"),
+            root.join("src/a.module.scss"),
+            root.join("lib"),
+            root.join("lib/deleted.module.scss"),
+        ]);
+        assert_eq!(
+            inputs.files,
+            vec![root.join("lib/b.module.scss"), root.join("src/a.module.scss")]
+        );
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].ends_with("lib, it is not a file"), "{}", warnings[0]);
+        assert!(warnings[1].contains("deleted.module.scss: "), "{}", warnings[1]);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let error = Filter::new(&[], &patterns(&["src/[legacy"])).unwrap_err();
+        assert!(error.starts_with("Invalid pattern src/[legacy: "), "{error}");
+    }
 }
@@ -1,84 +1,471 @@
 use env_logger::Env;
-use log::{debug, info};
-use std::{path::PathBuf, thread, time::Duration};
+use log::{debug, info, log_enabled, warn, Level};
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 
-use generator::generator::Generator;
-use loader::loader::get_scss_files;
+use config::config::{Config, PACKAGE_JSON_KEY};
+use generator::convention::ExportConvention;
+use generator::generator::{
+    is_generated, Generator, GeneratorOptions, Outcome, SortOrder, Summary, DEFAULT_BANNER,
+};
+use generator::templates::Template;
+use loader::loader::{
+    get_declaration_files, get_scss_files, is_scss_file, parse_path_list, Filter, Inputs,
+    DEFAULT_MODULES_PATTERN,
+};
+use parser::ast::Scope;
 use parser::scss::ScssFile;
 
+mod config;
 mod generator;
 mod lexer;
 mod loader;
 mod parser;
+mod watcher;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
+#[command(group(
+    ArgGroup::new("inputs")
+        .required(true)
+        .multiple(true)
+        .args(["path", "files", "stdin", "files_from"])
+))]
 struct Args {
-    /// Path to the root directory of your app
+    /// Path to the root directory of your app, can be repeated
     #[arg(short, long)]
-    path: String,
+    path: Vec<PathBuf>,
 
-    /// Log level for the application
-    #[arg(short, long, default_value = "info", value_enum)]
-    log_level: String,
+    /// Stylesheets to process without searching --path, like the staged files from lint-staged
+    files: Vec<PathBuf>,
 
-    #[arg(short, long, default_value_t = 4)]
-    /// The number of parallel threads to use for processing
-    threads: usize,
+    #[arg(long)]
+    /// Read stylesheets to process from stdin, one per line or separated by NUL bytes
+    stdin: bool,
+
+    #[arg(long)]
+    /// Read stylesheets to process from this file, one per line or separated by NUL bytes
+    files_from: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Stylesheets that are CSS modules and get declarations, relative to --path. Sass partials starting with _ are always skipped [default: **/*.module.{scss,css}]
+    modules_pattern: Option<String>,
+
+    #[arg(long)]
+    /// Only process stylesheets matching this glob relative to --path, like "src/**/*.module.scss". Can be repeated
+    include: Vec<String>,
+
+    #[arg(long)]
+    /// Skip files and directories matching this glob relative to --path, like "**/legacy". Can be repeated
+    exclude: Vec<String>,
+
+    #[arg(long, overrides_with = "ignore")]
+    /// Also process stylesheets ignored by .gitignore, .ignore or the global git excludes
+    no_ignore: bool,
+
+    #[arg(long, overrides_with = "no_ignore")]
+    /// Respect ignore files even if the config file sets noIgnore
+    ignore: bool,
+
+    #[arg(short, long)]
+    /// Config file, by default ts-scss-modules.toml or the "tsScssModules" key of package.json in --path or above
+    config: Option<PathBuf>,
+
+    /// Log level for the application [default: info]
+    #[arg(short, long)]
+    log_level: Option<String>,
+
+    #[arg(short, long)]
+    /// The number of parallel threads to use for processing [default: 4]
+    threads: Option<usize>,
+
+    #[arg(short, long, value_enum)]
+    /// Scope of classes outside of :global and :local, like css-loader's mode [default: local]
+    mode: Option<Scope>,
+
+    #[arg(short, long, value_enum)]
+    /// How class names and :export keys become keys of the styles object, like css-loader's exportLocalsConvention [default: asIs]
+    export_convention: Option<ExportConvention>,
+
+    #[arg(short, long, value_enum)]
+    /// Order of the keys in the generated declarations [default: alphabetical]
+    sort: Option<SortOrder>,
+
+    #[arg(long)]
+    /// Built-in template (default, interface or commonjs) or path to a Handlebars template [default: default]
+    template: Option<String>,
+
+    #[arg(long, overrides_with = "no_named_exports")]
+    /// Declare each key with `export const`, like css-loader's namedExport
+    named_exports: bool,
+
+    #[arg(long, overrides_with = "named_exports")]
+    /// Only export the styles object, even if the config file sets namedExports
+    no_named_exports: bool,
+
+    #[arg(long, overrides_with = "no_keep_default_export")]
+    /// Keep the default export next to the named exports
+    keep_default_export: bool,
+
+    #[arg(long, overrides_with = "keep_default_export")]
+    /// Drop the default export, even if the config file sets keepDefaultExport
+    no_keep_default_export: bool,
+
+    #[arg(long)]
+    /// Write nothing and fail when a declaration is missing, out of date or orphaned
+    check: bool,
+
+    #[arg(long)]
+    /// Comment at the top of generated declarations, below the `@generated` marker
+    banner: Option<String>,
+
+    #[arg(long, overrides_with = "no_source_hash")]
+    /// Add a hash of the stylesheet to the top of generated declarations
+    source_hash: bool,
+
+    #[arg(long, overrides_with = "source_hash")]
+    /// Leave out the hash, even if the config file sets sourceHash
+    no_source_hash: bool,
+
+    #[arg(long)]
+    /// Overwrite declarations that were not generated by ts-scss-modules
+    force: bool,
+
+    #[arg(long)]
+    /// Write declarations to this directory, mirroring their path relative to --path
+    out_dir: Option<PathBuf>,
+
+    #[arg(short, long, conflicts_with = "check")]
+    /// Keep running and update declarations when stylesheets change
+    watch: bool,
+
+    #[arg(long)]
+    /// Milliseconds without changes before a burst of saves is handled in --watch mode [default: 200]
+    debounce: Option<u64>,
+
+    #[arg(long, conflicts_with = "check")]
+    /// Delete generated declarations whose stylesheet is gone or has no classes
+    clean: bool,
+
+    #[arg(long, requires = "clean")]
+    /// List the declarations --clean would delete without deleting them
+    dry_run: bool,
+}
+
+/// A `--x`/`--no-x` pair, `None` when neither is given so the config file
+/// decides. The last one given wins.
+fn flag(enabled: bool, disabled: bool) -> Option<bool> {
+    match (enabled, disabled) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Resolves the output directory through symlinks like the roots, so the
+/// paths of declarations and stylesheets can be mapped onto each other. It is
+/// created unless nothing is written, then only its existing part is resolved.
+fn resolve_out_dir(out_dir: &Path, create: bool) -> Result<PathBuf, String> {
+    if out_dir.as_os_str().is_empty() {
+        return Err("--out-dir must not be empty".to_string());
+    }
+    let error = |e: io::Error| {
+        format!(
+            "Could not resolve output directory {}: {}",
+            out_dir.display(),
+            e
+        )
+    };
+    if create {
+        fs::create_dir_all(out_dir).map_err(error)?;
+    }
+    let out_dir = std::path::absolute(out_dir).map_err(error)?;
+    let existing = out_dir
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(&out_dir);
+    let missing = out_dir.strip_prefix(existing).unwrap_or(Path::new(""));
+    Ok(existing.canonicalize().map_err(error)?.join(missing))
+}
+
+/// Paths listed on stdin with --stdin and in the file of --files-from.
+fn read_path_lists(args: &Args) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    if args.stdin {
+        let mut content = Vec::new();
+        if let Err(e) = io::stdin().read_to_end(&mut content) {
+            return Err(format!("Could not read stdin: {}", e));
+        }
+        paths.extend(parse_path_list(&content));
+    }
+    if let Some(files_from) = &args.files_from {
+        match fs::read(files_from) {
+            Ok(content) => paths.extend(parse_path_list(&content)),
+            Err(e) => return Err(format!("Could not read {}: {}", files_from.display(), e)),
+        }
+    }
+    Ok(paths)
 }
 
-fn main() {
+/// The config given with --config or found from the inputs upwards.
+fn load_config(args: &Args, path: &Path) -> Result<Option<(PathBuf, Config)>, String> {
+    match &args.config {
+        Some(config_path) => match Config::load(config_path)? {
+            Some(config) => Ok(Some((config_path.clone(), config))),
+            None => Err(format!(
+                "{} has no \"{}\" key",
+                config_path.display(),
+                PACKAGE_JSON_KEY
+            )),
+        },
+        None => Config::find(path),
+    }
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
 
+    let paths = args.path.iter().chain(&args.files).cloned().collect::<Vec<_>>();
+    let (mut inputs, input_errors) = Inputs::resolve(&paths);
+    let listed = match read_path_lists(&args) {
+        Ok(listed) => listed,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let list_warnings = inputs.add_files(&listed);
+    let current_dir = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .expect("Could not resolve the working directory");
+    let base = inputs.base(&current_dir);
+    let (config_path, config) = match load_config(&args, &base) {
+        Ok(Some((config_path, config))) => (Some(config_path), config),
+        Ok(None) => (None, Config::default()),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
     // Initialize the logger with the specified log level
-    let env = Env::default().filter_or("TS_SCSS_LOG_LEVEL", args.log_level);
+    let log_level = args.log_level.clone().or(config.log_level).unwrap_or("info".to_string());
+    let env = Env::default().filter_or("TS_SCSS_LOG_LEVEL", log_level);
     env_logger::init_from_env(env);
 
-    let srcdir = PathBuf::from(args.path);
-    let absolute_path = srcdir
-        .canonicalize()
-        .unwrap()
-        .into_os_string()
-        .into_string()
-        .expect("Could not resolve path");
-    let result = get_scss_files(&absolute_path).collect::<Vec<_>>();
+    if let Some(config_path) = config_path {
+        info!("Using configuration from {}", config_path.display());
+    }
+    // Missing inputs fail the run once the others are processed, entries of
+    // lists only get a warning
+    for e in &input_errors {
+        eprintln!("{}", e);
+    }
+    for warning in &list_warnings {
+        warn!("{}", warning);
+    }
+    if args.watch && !inputs.files.is_empty() {
+        eprintln!("--watch only works with directories, not files");
+        return ExitCode::FAILURE;
+    }
+    let threads = args.threads.or(config.threads).unwrap_or(4);
+    if threads == 0 {
+        eprintln!("--threads must be at least 1");
+        return ExitCode::FAILURE;
+    }
+
+    // Patterns on the command line replace the ones of the config file
+    let include = if args.include.is_empty() {
+        config.include.unwrap_or_default()
+    } else {
+        args.include
+    };
+    let exclude = if args.exclude.is_empty() {
+        config.exclude.unwrap_or_default()
+    } else {
+        args.exclude
+    };
+    let ignore_files = !flag(args.no_ignore, args.ignore)
+        .or(config.no_ignore)
+        .unwrap_or(false);
+    let modules_pattern = args
+        .modules_pattern
+        .or(config.modules_pattern)
+        .unwrap_or(DEFAULT_MODULES_PATTERN.to_string());
+    let filter = match Filter::new(&include, &exclude)
+        .and_then(|filter| filter.with_modules_pattern(&modules_pattern))
+    {
+        Ok(filter) => filter.with_ignore_files(ignore_files),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // With files, --path is only what they are relative to and not searched
+    let scan = inputs.files.is_empty() && !args.stdin && args.files_from.is_none();
+    let roots: &[PathBuf] = if scan { &inputs.roots } else { &[] };
+    let mut stylesheets = BTreeSet::new();
+    for root in roots {
+        let root = root.to_string_lossy();
+        stylesheets.extend(get_scss_files(&root, &filter).map(|e| e.into_path()));
+    }
+    // Files given one by one are not searched for, only checked
+    for file in &inputs.files {
+        match inputs.root_of(file, &base) {
+            Some(root) if is_scss_file(root, file, &filter) => {
+                stylesheets.insert(file.clone());
+            }
+            Some(_) => warn!("Skipping {}, it is not a CSS module or is excluded", file.display()),
+            None => warn!("Skipping {}, it is outside of {}", file.display(), base.display()),
+        }
+    }
+    let result = stylesheets.into_iter().collect::<Vec<_>>();
     let file_count = result.len();
 
-    info!("Found {} .scss files parsing...", file_count);
+    info!("Found {} stylesheets, parsing...", file_count);
 
-    let chunk_size = file_count.div_ceil(args.threads);
+    let chunk_size = file_count.div_ceil(threads).max(1);
 
-    let chunks: Vec<Vec<walkdir::DirEntry>> = result
+    let chunks: Vec<Vec<PathBuf>> = result
         .chunks(chunk_size)
         .map(|chunk| chunk.to_vec())
         .collect();
 
+    let out_dir = match args
+        .out_dir
+        .or(config.out_dir)
+        .map(|out_dir| resolve_out_dir(&out_dir, !args.check && !args.dry_run))
+        .transpose()
+    {
+        Ok(out_dir) => out_dir,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Flags on the command line win over the config file
+    let named_exports = flag(args.named_exports, args.no_named_exports)
+        .or(config.named_exports)
+        .unwrap_or(false);
+    let keep_default_export = flag(args.keep_default_export, args.no_keep_default_export)
+        .or(config.keep_default_export)
+        .unwrap_or(false);
+    // keepDefaultExport of the config file has no effect without named exports
+    if args.keep_default_export && !named_exports {
+        eprintln!("--keep-default-export requires --named-exports");
+        return ExitCode::FAILURE;
+    }
+    let options = GeneratorOptions {
+        mode: args.mode.or(config.mode).unwrap_or_default(),
+        export_convention: args
+            .export_convention
+            .or(config.export_convention)
+            .unwrap_or_default(),
+        sort: args.sort.or(config.sort).unwrap_or_default(),
+        named_exports,
+        keep_default_export: keep_default_export && named_exports,
+        banner: args
+            .banner
+            .or(config.banner)
+            .unwrap_or(DEFAULT_BANNER.to_string()),
+        source_hash: flag(args.source_hash, args.no_source_hash)
+            .or(config.source_hash)
+            .unwrap_or(false),
+        force: args.force,
+        out_dir,
+    };
+    let template = args
+        .template
+        .or(config.template)
+        .unwrap_or("default".to_string());
+    // Template errors are reported before any file is written
+    let generator = match Template::load(&template)
+        .and_then(|template| Generator::new(options).with_template(template))
+    {
+        Ok(generator) => Arc::new(generator.with_root(&base)),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (check, clean, dry_run) = (args.check, args.clean, args.dry_run);
+    let (watch, debounce) = (args.watch, args.debounce.or(config.debounce).unwrap_or(200));
+
     let mut handles = Vec::new();
 
     for (thread_id, chunk) in chunks.into_iter().enumerate() {
+        let generator = Arc::clone(&generator);
         let handle = thread::spawn(move || {
-            let generator = Generator::new();
+            let mut summary = Summary::default();
             for file in &chunk {
-                if Args::parse().log_level == "debug" {
+                if log_enabled!(Level::Debug) {
                     debug!(
                         "Thread {} processing file: {}",
                         thread_id,
-                        file.path().display()
+                        file.display()
                     );
                 } else {
-                    info!("Parsing: {}", file.file_name().to_str().unwrap());
+                    info!("Parsing: {}", file_name(file));
+                }
+                let scss_file = match ScssFile::new(file) {
+                    Ok(scss_file) => scss_file,
+                    Err(e) => {
+                        eprintln!(
+                            "Error generating declaration for {}: {}",
+                            file_name(file),
+                            e
+                        );
+                        summary.add(generator.declaration_path_for(file), &Err(e));
+                        continue;
+                    }
+                };
+                for diagnostic in &scss_file.diagnostics {
+                    warn!("{}", diagnostic);
+                }
+                debug!(
+                    "Classes found: {:?}",
+                    generator
+                        .local_classes(&scss_file)
+                        .iter()
+                        .map(|class| &class.name)
+                        .collect::<Vec<_>>()
+                );
+                let declaration_path = generator.declaration_path(&scss_file);
+                let mut result = if check {
+                    generator.check_declaration(&scss_file)
+                } else {
+                    generator.generate_declaration(&scss_file)
+                };
+                if clean && result == Ok(Outcome::Orphaned) {
+                    result = generator.remove_declaration(&declaration_path, dry_run);
                 }
-                let scss_file = ScssFile::new(file.path());
-                debug!("Classes found: {:?}", scss_file.classes());
-                if let Err(e) = generator.generate_declaration(&scss_file) {
+                if let Err(e) = &result {
                     eprintln!(
                         "Error generating declaration for {}: {}",
-                        file.file_name().to_str().unwrap(),
+                        file_name(file),
                         e
                     );
                 }
+                summary.add(declaration_path, &result);
                 thread::sleep(Duration::from_millis(1));
             }
             debug!(
@@ -86,16 +473,99 @@ fn main() {
                 thread_id,
                 chunk.len()
             );
+            summary
         });
 
         handles.push(handle);
     }
 
     // Wait for all threads to complete
+    let mut summary = Summary::default();
     for handle in handles {
-        let Err(_) = handle.join() else { continue };
-        eprintln!("A worker thread panicked");
+        match handle.join() {
+            Ok(thread_summary) => summary.merge(thread_summary),
+            Err(_) => eprintln!("A worker thread panicked"),
+        }
     }
 
     info!("Parsed {} files successfully.", file_count);
+
+    summary.failed += input_errors.len();
+
+    // Declarations of stylesheets that were deleted or renamed, files given
+    // one by one have no directory to search
+    for root in roots {
+        let declaration_dir = generator.declaration_dir(root);
+        let declaration_dir = declaration_dir.to_string_lossy();
+        for file in get_declaration_files(&declaration_dir, &filter) {
+            if generator.source_path(file.path()).exists() {
+                continue;
+            }
+            let result = if clean {
+                generator.remove_declaration(file.path(), dry_run)
+            } else if is_generated(file.path()) {
+                Ok(Outcome::Orphaned)
+            } else {
+                continue;
+            };
+            if let Err(e) = &result {
+                eprintln!("{}", e);
+            }
+            summary.add(file.path().to_path_buf(), &result);
+        }
+    }
+    summary.changes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    if !check {
+        for (declaration_path, outcome) in &summary.changes {
+            match outcome {
+                Outcome::Removed if dry_run => info!("Would remove {}", declaration_path.display()),
+                Outcome::Removed => info!("Removed {}", declaration_path.display()),
+                _ => {}
+            }
+        }
+        info!(
+            "Declarations: {} created, {} updated, {} unchanged, {} without classes, {} orphaned, {} removed, {} failed.",
+            summary.created,
+            summary.updated,
+            summary.unchanged,
+            summary.skipped,
+            summary.orphaned,
+            summary.removed,
+            summary.failed
+        );
+        if summary.orphaned > 0 && !clean {
+            info!("Run with --clean to remove the orphaned declarations.");
+        }
+        if watch {
+            if let Err(e) = watcher::watcher::watch(
+                &generator,
+                &inputs.roots,
+                &filter,
+                Duration::from_millis(debounce),
+            ) {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        return if summary.failed == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
+    for (declaration_path, outcome) in &summary.changes {
+        eprintln!("{}: {}", outcome.check_message(), declaration_path.display());
+    }
+    if summary.changes.is_empty() && summary.failed == 0 {
+        info!("All declarations are up to date.");
+        ExitCode::SUCCESS
+    } else {
+        eprintln!(
+            "{} missing, {} out of date, {} orphaned, {} failed. Run without --check, and with --clean for orphans, to update them.",
+            summary.created, summary.updated, summary.orphaned, summary.failed
+        );
+        ExitCode::FAILURE
+    }
 }
@@ -0,0 +1,153 @@
+use crate::lexer::lexer::Span;
+
+/// Root of a parsed SCSS file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stylesheet {
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Node {
+    Rule(Rule),               // .a, .b { ... }
+    AtRule(AtRule),           // @media, @include, @mixin, ...
+    Declaration(Declaration), // color: red; and $variable: value;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub selectors: Vec<Selector>,
+    pub children: Vec<Node>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AtRule {
+    pub name: String,                // Name without the '@', like media
+    pub params: String,              // Everything between the name and the block
+    pub children: Option<Vec<Node>>, // None for statements like @include foo;
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Declaration {
+    pub property: String,
+    pub value: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub parts: Vec<SelectorPart>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorPart {
+    Parent(String),                // & with an optional suffix, like &__title
+    Class(String, Span),           // .name
+    Element(String),               // div, from, to
+    Pseudo(String, Vec<Selector>), // :hover, :not(.a), ::before
+    Combinator(char),              // ' ', '>', '+' or '~'
+    Other(String),                 // Ids, attributes, placeholders, interpolations
+}
+
+/// CSS Modules scope of a class. Local classes end up in the styles object.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    #[default]
+    Local,
+    Global,
+}
+
+impl Selector {
+    /// Classes used anywhere in the selector, including pseudo-class arguments,
+    /// with their scope. `mode` is the scope of classes outside of `:global`
+    /// and `:local`.
+    pub fn classes(&self, mode: Scope) -> Vec<(&str, Span, Scope)> {
+        let mut scope = mode;
+        let mut classes = Vec::new();
+        for part in &self.parts {
+            match part {
+                SelectorPart::Class(name, span) => classes.push((name.as_str(), *span, scope)),
+                SelectorPart::Pseudo(name, arguments) => {
+                    let argument_scope = match name.as_str() {
+                        "global" => Scope::Global,
+                        "local" => Scope::Local,
+                        _ => scope,
+                    };
+                    if arguments.is_empty() && argument_scope != scope {
+                        // `:global .a .b` switches the scope for the rest of the selector
+                        scope = argument_scope;
+                    }
+                    for argument in arguments {
+                        classes.extend(argument.classes(argument_scope));
+                    }
+                }
+                _ => {}
+            }
+        }
+        classes
+    }
+
+    /// Resolves `&` against the selectors of the enclosing rule, one result
+    /// per parent. A suffix like `&__title` is appended to the last part of
+    /// the parent, and selectors without `&` become descendants of it.
+    pub fn resolve(&self, parents: &[Selector]) -> Vec<Selector> {
+        if parents.is_empty() {
+            return vec![self.clone()];
+        }
+        let has_parent = self
+            .parts
+            .iter()
+            .any(|part| matches!(part, SelectorPart::Parent(_)));
+
+        parents
+            .iter()
+            .map(|parent| {
+                let mut parts = Vec::new();
+                if !has_parent {
+                    parts.extend(parent.parts.iter().cloned());
+                    if !matches!(self.parts.first(), Some(SelectorPart::Combinator(_))) {
+                        parts.push(SelectorPart::Combinator(' '));
+                    }
+                }
+                for part in &self.parts {
+                    match part {
+                        SelectorPart::Parent(suffix) => {
+                            parts.extend(parent.parts.iter().cloned());
+                            if !suffix.is_empty() {
+                                append_suffix(&mut parts, suffix, self.span);
+                            }
+                        }
+                        part => parts.push(part.clone()),
+                    }
+                }
+                Selector {
+                    parts,
+                    span: self.span,
+                }
+            })
+            .collect()
+    }
+}
+
+fn append_suffix(parts: &mut Vec<SelectorPart>, suffix: &str, span: Span) {
+    match parts.pop() {
+        Some(SelectorPart::Class(name, _)) => {
+            parts.push(SelectorPart::Class(format!("{name}{suffix}"), span))
+        }
+        Some(SelectorPart::Element(name)) => {
+            parts.push(SelectorPart::Element(format!("{name}{suffix}")))
+        }
+        Some(SelectorPart::Other(text)) => {
+            parts.push(SelectorPart::Other(format!("{text}{suffix}")))
+        }
+        // There is nothing to attach the suffix to, like `:hover { &-x {} }`
+        Some(part) => {
+            parts.push(part);
+            parts.push(SelectorPart::Other(suffix.to_string()));
+        }
+        None => parts.push(SelectorPart::Other(suffix.to_string())),
+    }
+}
@@ -1 +1,5 @@
+pub(crate) mod ast;
+#[allow(clippy::module_inception)]
+pub(crate) mod parser;
 pub(crate) mod scss;
+pub(crate) mod visitor;
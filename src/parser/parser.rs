@@ -0,0 +1,677 @@
+use crate::lexer::lexer::{Operator, Span, Token, TokenKind};
+use crate::parser::ast::{AtRule, Declaration, Node, Rule, Selector, SelectorPart, Stylesheet};
+
+/// Builds a [`Stylesheet`] from the lexer's tokens.
+///
+/// The parser never fails: unbalanced braces are closed at the end of the
+/// file and statements it cannot make sense of are dropped.
+pub struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a str, tokens: &'a [Token]) -> Self {
+        Parser {
+            source,
+            tokens,
+            position: 0,
+        }
+    }
+
+    pub fn parse(mut self) -> Stylesheet {
+        Stylesheet {
+            children: self.parse_block(false),
+        }
+    }
+
+    fn parse_block(&mut self, nested: bool) -> Vec<Node> {
+        let tokens = self.tokens;
+        let mut nodes = Vec::new();
+        while let Some(token) = tokens.get(self.position) {
+            match &token.kind {
+                TokenKind::Op(Operator::RBrace) => {
+                    self.position += 1;
+                    if nested {
+                        break;
+                    } // A stray '}' at the top level is skipped
+                }
+                kind if is_trivia(kind) || *kind == TokenKind::Op(Operator::Semicolon) => {
+                    self.position += 1;
+                }
+                TokenKind::Property(property) if !self.is_pseudo_class(token) => {
+                    self.position += 1;
+                    let value = self.declaration_value(self.text(token.span));
+                    nodes.push(Node::Declaration(Declaration {
+                        property: property.trim().to_string(),
+                        value,
+                        span: token.span,
+                    }));
+                }
+                _ => nodes.extend(self.parse_rule_or_statement()),
+            }
+        }
+        nodes
+    }
+
+    fn parse_rule_or_statement(&mut self) -> Option<Node> {
+        let tokens = self.tokens;
+        let start = self.position;
+        let mut depth = 0;
+        while let Some(token) = tokens.get(self.position) {
+            match token.kind {
+                TokenKind::Op(Operator::LParen) => depth += 1,
+                TokenKind::Op(Operator::RParen) => depth -= 1,
+                TokenKind::Op(Operator::LBrace) | TokenKind::Op(Operator::RBrace) => break,
+                TokenKind::Op(Operator::Semicolon) if depth <= 0 => break,
+                // A declaration carries its own ';', so a statement missing one ends here
+                TokenKind::Property(_) if depth <= 0 && !self.is_pseudo_class(token) => break,
+                _ => {}
+            }
+            self.position += 1;
+        }
+        let prelude = trim_trivia(&tokens[start..self.position]);
+
+        let children = match tokens.get(self.position).map(|t| &t.kind) {
+            Some(TokenKind::Op(Operator::LBrace)) => {
+                self.position += 1;
+                Some(self.parse_block(true))
+            }
+            Some(TokenKind::Op(Operator::Semicolon)) => {
+                self.position += 1;
+                None
+            }
+            _ => None, // A missing ';' before '}' or the end of the file
+        };
+        let span = self.span(&tokens[start..self.position]);
+
+        if is_at_rule(prelude) {
+            return Some(Node::AtRule(self.at_rule(prelude, children, span)));
+        }
+        match children {
+            Some(children) => Some(Node::Rule(Rule {
+                selectors: self.parse_selectors(prelude),
+                children,
+                span,
+            })),
+            None => {
+                // Anything with a colon is treated as a declaration, like $gap: 4px
+                let text = self.text(self.span(prelude));
+                let (property, _) = text.split_once(':')?;
+                Some(Node::Declaration(Declaration {
+                    property: property.trim().to_string(),
+                    value: self.declaration_value(text),
+                    span,
+                }))
+            }
+        }
+    }
+
+    fn at_rule(&self, prelude: &[Token], children: Option<Vec<Node>>, span: Span) -> AtRule {
+        let text = self.text(self.span(prelude)).trim_start_matches('@');
+        let name_length = text
+            .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(text.len());
+        let (name, params) = text.split_at(name_length);
+
+        // `@at-root .a { ... }` holds a regular rule, keep it as one
+        let selector = trim_trivia(&prelude[1..]);
+        let children = match children {
+            Some(children) if name == "at-root" && !selector.is_empty() => {
+                Some(vec![Node::Rule(Rule {
+                    selectors: self.parse_selectors(selector),
+                    children,
+                    span,
+                })])
+            }
+            children => children,
+        };
+
+        AtRule {
+            name: name.to_string(),
+            params: params.trim().to_string(),
+            children,
+            span,
+        }
+    }
+
+    fn parse_selectors(&self, tokens: &[Token]) -> Vec<Selector> {
+        split_top_level(tokens, ",")
+            .into_iter()
+            .map(trim_trivia)
+            .filter(|selector| !selector.is_empty())
+            .map(|selector| self.parse_selector(selector))
+            .collect()
+    }
+
+    fn parse_selector(&self, tokens: &[Token]) -> Selector {
+        let mut builder = SelectorBuilder::default();
+        let mut i = 0;
+        while let Some(token) = tokens.get(i) {
+            i += 1;
+            match &token.kind {
+                kind if is_trivia(kind) => builder.space = true,
+                TokenKind::Class(nested) => {
+                    if *nested {
+                        builder.push(SelectorPart::Parent(String::new()));
+                        if token.value.is_empty() {
+                            builder.space = true; // `& .child`
+                            continue;
+                        }
+                    }
+                    let end = adjacent_end(tokens, i);
+                    if end > i {
+                        // A class built with interpolation, like .icon-#{$name}
+                        let span = self.span(&tokens[i - 1..end]);
+                        builder.push(SelectorPart::Other(self.text(span).trim().to_string()));
+                        i = end;
+                    } else if token.value.is_empty() {
+                        builder.push(SelectorPart::Other(self.text(token.span).to_string()));
+                    } else {
+                        builder.push(SelectorPart::Class(token.value.clone(), token.span));
+                    }
+                }
+                TokenKind::Property(name) => {
+                    // `&:hover` comes out of the lexer as an empty property
+                    builder.push(SelectorPart::Parent(String::new()));
+                    let (arguments, end) = self.pseudo_arguments(tokens, i);
+                    builder.push(SelectorPart::Pseudo(name.clone(), arguments));
+                    i = end;
+                }
+                TokenKind::Op(Operator::Colon) => {
+                    while tokens.get(i).map(|t| &t.kind) == Some(&TokenKind::Op(Operator::Colon)) {
+                        i += 1; // `::before`
+                    }
+                    let Some(name) = tokens.get(i).filter(|t| t.kind == TokenKind::Element) else {
+                        continue;
+                    };
+                    i += 1;
+                    let mut words = name.value.split_whitespace();
+                    let pseudo = words.next().unwrap_or_default().to_string();
+                    let rest = words.collect::<Vec<_>>();
+                    if rest.is_empty() && !name.value.ends_with(char::is_whitespace) {
+                        let (arguments, end) = self.pseudo_arguments(tokens, i);
+                        builder.push(SelectorPart::Pseudo(pseudo, arguments));
+                        i = end;
+                    } else {
+                        builder.push(SelectorPart::Pseudo(pseudo, Vec::new()));
+                        builder.space = true;
+                        builder.push_elements(&rest.join(" "));
+                    }
+                }
+                TokenKind::Element => builder.push_elements(&token.value),
+                TokenKind::Op(Operator::Plus) => builder.push(SelectorPart::Combinator('+')),
+                TokenKind::Unknown if token.value == ">" || token.value == "~" => builder.push(
+                    SelectorPart::Combinator(token.value.chars().next().unwrap()),
+                ),
+                TokenKind::Parent => builder.push(SelectorPart::Parent(token.value.clone())),
+                TokenKind::Unknown if token.value == "[" => {
+                    // Attribute selectors, kept as they are
+                    let end = tokens[i..]
+                        .iter()
+                        .position(|t| t.kind == TokenKind::Unknown && t.value == "]")
+                        .map(|offset| i + offset + 1)
+                        .unwrap_or(tokens.len());
+                    let span = self.span(&tokens[i - 1..end]);
+                    builder.push(SelectorPart::Other(self.text(span).to_string()));
+                    i = end;
+                }
+                _ => {
+                    // Ids and placeholders like #main or %button take the next word along
+                    let end = match tokens.get(i) {
+                        Some(next)
+                            if (token.value == "#" || token.value == "%")
+                                && next.kind == TokenKind::Element
+                                && next.span.start == token.span.end =>
+                        {
+                            i + 1
+                        }
+                        _ => i,
+                    };
+                    let span = self.span(&tokens[i - 1..end]);
+                    builder.push(SelectorPart::Other(self.text(span).trim().to_string()));
+                    builder.space = self.text(span).ends_with(char::is_whitespace);
+                    i = end;
+                }
+            }
+        }
+
+        Selector {
+            parts: builder.parts,
+            span: self.span(tokens),
+        }
+    }
+
+    /// Parses `(...)` after a pseudo-class, if present, as a selector list.
+    /// Returns the arguments and the index of the first token after them.
+    fn pseudo_arguments(&self, tokens: &[Token], start: usize) -> (Vec<Selector>, usize) {
+        if tokens.get(start).map(|t| &t.kind) != Some(&TokenKind::Op(Operator::LParen)) {
+            return (Vec::new(), start);
+        }
+        let mut depth = 0;
+        for (offset, token) in tokens[start..].iter().enumerate() {
+            match token.kind {
+                TokenKind::Op(Operator::LParen) => depth += 1,
+                TokenKind::Op(Operator::RParen) => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                let end = start + offset;
+                return (self.parse_selectors(&tokens[start + 1..end]), end + 1);
+            }
+        }
+        (self.parse_selectors(&tokens[start + 1..]), tokens.len())
+    }
+
+    fn is_pseudo_class(&self, token: &Token) -> bool {
+        token.value.is_empty() && self.text(token.span).starts_with('&')
+    }
+
+    /// The value of a declaration, taken from its source text so spacing and
+    /// quotes are preserved.
+    fn declaration_value(&self, text: &str) -> String {
+        let value = text
+            .split_once(':')
+            .map(|(_, value)| value)
+            .unwrap_or_default();
+        value.trim().trim_end_matches(';').trim_end().to_string()
+    }
+
+    fn text(&self, span: Span) -> &'a str {
+        &self.source[span.start..span.end]
+    }
+
+    fn span(&self, tokens: &[Token]) -> Span {
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => Span {
+                end: last.span.end,
+                ..first.span
+            },
+            _ => Span::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SelectorBuilder {
+    parts: Vec<SelectorPart>,
+    space: bool, // Whitespace seen since the last part, a descendant combinator
+}
+
+impl SelectorBuilder {
+    fn push(&mut self, part: SelectorPart) {
+        let after_combinator =
+            matches!(self.parts.last(), None | Some(SelectorPart::Combinator(_)));
+        if let SelectorPart::Combinator(_) = part {
+            if let Some(SelectorPart::Combinator(' ')) = self.parts.last() {
+                self.parts.pop();
+            }
+        } else if self.space && !after_combinator {
+            self.parts.push(SelectorPart::Combinator(' '));
+        }
+        self.space = false;
+        self.parts.push(part);
+    }
+
+    /// Pushes the words of an element token, like `div span `.
+    fn push_elements(&mut self, value: &str) {
+        for word in value.split_whitespace() {
+            self.push(SelectorPart::Element(word.to_string()));
+            self.space = true;
+        }
+        self.space = value.ends_with(char::is_whitespace);
+    }
+}
+
+fn is_trivia(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Indent(_) | TokenKind::Op(Operator::NewLine) | TokenKind::Comment
+    )
+}
+
+fn is_at_rule(prelude: &[Token]) -> bool {
+    matches!(
+        prelude.first().map(|t| &t.kind),
+        Some(
+            TokenKind::Import
+                | TokenKind::Include
+                | TokenKind::Mixin
+                | TokenKind::Media
+                | TokenKind::AtKeyword
+        )
+    )
+}
+
+fn trim_trivia(tokens: &[Token]) -> &[Token] {
+    let start = tokens
+        .iter()
+        .position(|t| !is_trivia(&t.kind))
+        .unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(|t| !is_trivia(&t.kind))
+        .map(|i| i + 1)
+        .unwrap_or(start);
+    &tokens[start..end]
+}
+
+/// Splits tokens at `separator` unknown tokens outside of parentheses.
+fn split_top_level<'t>(tokens: &'t [Token], separator: &str) -> Vec<&'t [Token]> {
+    let mut groups = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Op(Operator::LParen) => depth += 1,
+            TokenKind::Op(Operator::RParen) => depth -= 1,
+            TokenKind::Unknown if depth == 0 && token.value == separator => {
+                groups.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    groups.push(&tokens[start..]);
+    groups
+}
+
+/// Index after the interpolations and words directly attached to the token
+/// before `start`, or `start` when there is no interpolation.
+fn adjacent_end(tokens: &[Token], start: usize) -> usize {
+    let mut end = start;
+    let mut interpolated = false;
+    while let (Some(previous), Some(token)) = (tokens.get(end - 1), tokens.get(end)) {
+        let attached =
+            previous.span.end == token.span.start && !previous.value.ends_with(char::is_whitespace);
+        match token.kind {
+            TokenKind::Interpolation if attached => interpolated = true,
+            TokenKind::Element | TokenKind::Unknown if attached && token.value != "," => {}
+            _ => break,
+        }
+        end += 1;
+    }
+    if interpolated {
+        end
+    } else {
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer::Lexer;
+
+    fn parse(input: &str) -> Stylesheet {
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        Parser::new(input, &tokens).parse()
+    }
+
+    fn rule(node: &Node) -> &Rule {
+        match node {
+            Node::Rule(rule) => rule,
+            node => panic!("Expected a rule, got {:?}", node),
+        }
+    }
+
+    fn class(name: &str) -> SelectorPart {
+        SelectorPart::Class(name.to_string(), Span::default())
+    }
+
+    /// Selector parts with class spans cleared, for comparisons.
+    fn parts(selector: &Selector) -> Vec<SelectorPart> {
+        selector
+            .parts
+            .iter()
+            .map(|part| match part {
+                SelectorPart::Class(name, _) => class(name),
+                SelectorPart::Pseudo(name, arguments) => SelectorPart::Pseudo(
+                    name.clone(),
+                    arguments
+                        .iter()
+                        .map(|argument| Selector {
+                            parts: parts(argument),
+                            span: Span::default(),
+                        })
+                        .collect(),
+                ),
+                part => part.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_with_declarations() {
+        let stylesheet = parse(".card {\n  display: flex;\n  color: $primary\n}\n");
+        assert_eq!(stylesheet.children.len(), 1);
+        let card = rule(&stylesheet.children[0]);
+        assert_eq!(parts(&card.selectors[0]), vec![class("card")]);
+        assert_eq!(
+            card.children,
+            vec![
+                Node::Declaration(Declaration {
+                    property: "display".to_string(),
+                    value: "flex".to_string(),
+                    span: Span {
+                        start: 10,
+                        end: 24,
+                        line: 2,
+                        column: 3
+                    },
+                }),
+                Node::Declaration(Declaration {
+                    property: "color".to_string(),
+                    value: "$primary".to_string(),
+                    span: Span {
+                        start: 27,
+                        end: 43,
+                        line: 3,
+                        column: 3
+                    },
+                }),
+            ]
+        );
+        assert_eq!(card.span.line, 1);
+        assert_eq!(card.span.end, 44);
+    }
+
+    #[test]
+    fn test_nested_rules() {
+        let stylesheet = parse(".a {\n  .b {\n    .c { color: red; }\n  }\n}\n.d {}\n");
+        assert_eq!(stylesheet.children.len(), 2);
+        let a = rule(&stylesheet.children[0]);
+        let b = rule(&a.children[0]);
+        let c = rule(&b.children[0]);
+        assert_eq!(parts(&c.selectors[0]), vec![class("c")]);
+        assert_eq!(c.children.len(), 1);
+        assert_eq!(
+            parts(&rule(&stylesheet.children[1]).selectors[0]),
+            vec![class("d")]
+        );
+    }
+
+    #[test]
+    fn test_selector_list_and_combinators() {
+        let stylesheet = parse(".a > .b, .c .d,\n.e + div span {}");
+        let selectors = &rule(&stylesheet.children[0]).selectors;
+        assert_eq!(selectors.len(), 3);
+        assert_eq!(
+            parts(&selectors[0]),
+            vec![class("a"), SelectorPart::Combinator('>'), class("b")]
+        );
+        assert_eq!(
+            parts(&selectors[1]),
+            vec![class("c"), SelectorPart::Combinator(' '), class("d")]
+        );
+        assert_eq!(
+            parts(&selectors[2]),
+            vec![
+                class("e"),
+                SelectorPart::Combinator('+'),
+                SelectorPart::Element("div".to_string()),
+                SelectorPart::Combinator(' '),
+                SelectorPart::Element("span".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parent_and_pseudo_selectors() {
+        let stylesheet = parse(".a {\n  &.b, &:hover, & .c {}\n  li:not(.d)::before {}\n}");
+        let a = rule(&stylesheet.children[0]);
+        let nested = &rule(&a.children[0]).selectors;
+        assert_eq!(
+            parts(&nested[0]),
+            vec![SelectorPart::Parent(String::new()), class("b")]
+        );
+        assert_eq!(
+            parts(&nested[1]),
+            vec![
+                SelectorPart::Parent(String::new()),
+                SelectorPart::Pseudo("hover".to_string(), Vec::new())
+            ]
+        );
+        assert_eq!(
+            parts(&nested[2]),
+            vec![
+                SelectorPart::Parent(String::new()),
+                SelectorPart::Combinator(' '),
+                class("c")
+            ]
+        );
+        let pseudo = &rule(&a.children[1]).selectors[0];
+        assert_eq!(
+            parts(pseudo),
+            vec![
+                SelectorPart::Element("li".to_string()),
+                SelectorPart::Pseudo(
+                    "not".to_string(),
+                    vec![Selector {
+                        parts: vec![class("d")],
+                        span: Span::default()
+                    }]
+                ),
+                SelectorPart::Pseudo("before".to_string(), Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_at_rules() {
+        let stylesheet = parse(
+            "@use 'sass:math';\n@include reset;\n@media (max-width: 600px) {\n  .a {}\n}\n@keyframes spin { from { opacity: 0; } }\n",
+        );
+        let at_rules = stylesheet
+            .children
+            .iter()
+            .map(|node| match node {
+                Node::AtRule(at_rule) => at_rule,
+                node => panic!("Expected an at-rule, got {:?}", node),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            at_rules
+                .iter()
+                .map(|r| (r.name.as_str(), r.params.as_str(), r.children.is_some()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("use", "'sass:math'", false),
+                ("include", "reset", false),
+                ("media", "(max-width: 600px)", true),
+                ("keyframes", "spin", true),
+            ]
+        );
+        let media = at_rules[2].children.as_ref().unwrap();
+        assert_eq!(parts(&rule(&media[0]).selectors[0]), vec![class("a")]);
+    }
+
+    #[test]
+    fn test_at_root_keeps_its_selector() {
+        let stylesheet = parse(".a {\n  @at-root .b { color: red; }\n}");
+        let Node::AtRule(at_root) = &rule(&stylesheet.children[0]).children[0] else {
+            panic!("Expected an at-rule");
+        };
+        let children = at_root.children.as_ref().unwrap();
+        assert_eq!(parts(&rule(&children[0]).selectors[0]), vec![class("b")]);
+    }
+
+    #[test]
+    fn test_variable_declarations() {
+        let stylesheet = parse("$gap: 4px;\n--radius: 2px;\n");
+        assert_eq!(
+            stylesheet
+                .children
+                .iter()
+                .map(|node| match node {
+                    Node::Declaration(d) => (d.property.as_str(), d.value.as_str()),
+                    node => panic!("Expected a declaration, got {:?}", node),
+                })
+                .collect::<Vec<_>>(),
+            vec![("$gap", "4px"), ("--radius", "2px")]
+        );
+    }
+
+    #[test]
+    fn test_interpolated_and_attribute_selectors() {
+        let stylesheet = parse(".icon-#{$name} {}\n[data-open] .menu {}\n#main {}");
+        assert_eq!(
+            parts(&rule(&stylesheet.children[0]).selectors[0]),
+            vec![SelectorPart::Other(".icon-#{$name}".to_string())]
+        );
+        assert_eq!(
+            parts(&rule(&stylesheet.children[1]).selectors[0]),
+            vec![
+                SelectorPart::Other("[data-open]".to_string()),
+                SelectorPart::Combinator(' '),
+                class("menu")
+            ]
+        );
+        assert_eq!(
+            parts(&rule(&stylesheet.children[2]).selectors[0]),
+            vec![SelectorPart::Other("#main".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_braces() {
+        let stylesheet = parse("}\n.a {\n  .b {\n");
+        assert_eq!(stylesheet.children.len(), 1);
+        let a = rule(&stylesheet.children[0]);
+        assert_eq!(parts(&rule(&a.children[0]).selectors[0]), vec![class("b")]);
+    }
+
+    #[test]
+    fn test_layout_fixture_structure() {
+        let input = include_str!("../../tests/fixtures/layout.module.scss");
+        let stylesheet = parse(input);
+        let summary = stylesheet
+            .children
+            .iter()
+            .map(|node| match node {
+                Node::Rule(rule) => format!("rule {}", rule.children.len()),
+                Node::AtRule(at_rule) => format!("@{} {}", at_rule.name, at_rule.params),
+                Node::Declaration(d) => format!("{}: {}", d.property, d.value),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                "$sidebar-width: 240px",
+                "@mixin respond-to($breakpoint)",
+                "rule 5",
+                "rule 3",
+                "rule 2",
+                "@media screen and (max-width: 600px)",
+                "@supports (display: grid)",
+            ]
+        );
+        let container = rule(&stylesheet.children[2]);
+        let Node::Declaration(areas) = &container.children[2] else {
+            panic!("Expected a declaration");
+        };
+        assert_eq!(areas.value, "'sidebar content'");
+        assert_eq!(areas.span.line, 14);
+    }
+}
@@ -1,38 +1,378 @@
 use std::{
     collections::HashSet, fs::{self}, path::Path
 };
-use crate::lexer::lexer::{Lexer, Token, TokenKind};
+use crate::lexer::lexer::{Diagnostic, Lexer, Span, Token};
+use crate::parser::ast::{Node, Rule, Scope, Selector, SelectorPart, Stylesheet};
+use crate::parser::parser::Parser;
+use crate::parser::visitor::{walk_nodes, Visitor};
 
 
 #[derive(Debug)]
 pub struct ScssFile {
-    pub tokens: Vec<Token>,
+    pub stylesheet: Stylesheet,
     pub file_path: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub source_hash: u64, // FNV-1a of the content
 }
 
 impl ScssFile {
-    pub fn new(path: &Path) -> Self {
-        let content = fs::read_to_string(path).unwrap();
-        let lexer = Lexer::new(&content);
-        let tokens = lexer.collect::<Vec<Token>>();
+    /// Reads and parses a stylesheet, failing for unreadable and non-UTF-8
+    /// files.
+    pub fn new(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Ok(Self::parse(&content, &path.display().to_string()))
+    }
+
+    pub fn parse(content: &str, file_path: &str) -> Self {
+        let mut lexer = Lexer::new(content).with_file_path(file_path);
+        let tokens = lexer.by_ref().collect::<Vec<Token>>();
+        let diagnostics = lexer.diagnostics().to_vec();
+        let stylesheet = Parser::new(content, &tokens).parse();
         Self {
-            tokens,
-            file_path: path.display().to_string(),
+            stylesheet,
+            file_path: file_path.to_string(),
+            diagnostics,
+            source_hash: fnv1a(content.as_bytes()),
         }
     }
 
-    pub fn classes(&self) -> HashSet<String> {
-        let mut classes = HashSet::new();
-        for token in &self.tokens {
-            if let TokenKind::Class(false) = token.kind {
-                classes.insert(token.value.to_string());
-            }
+    /// Classes of the file with their scope, in order of first appearance. A
+    /// class used both globally and locally is returned once for each scope.
+    pub fn classes(&self, mode: Scope) -> Vec<ClassName> {
+        let mut collector = ClassCollector {
+            mode,
+            classes: Vec::new(),
+            seen: HashSet::new(),
+            parents: Vec::new(),
+        };
+        collector.visit_stylesheet(&self.stylesheet);
+        collector.classes
+    }
+
+    /// Values shared with JavaScript through ICSS `:export { name: value; }`
+    /// blocks at the top level of the file.
+    pub fn exports(&self) -> Vec<Export> {
+        self.stylesheet
+            .children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Rule(rule) if is_export_block(rule) => Some(&rule.children),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|node| match node {
+                Node::Declaration(declaration) => Some(Export {
+                    name: declaration.property.clone(),
+                    value: declaration.value.clone(),
+                    span: declaration.span,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// 64 bit FNV-1a, stable across runs and platforms unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn is_export_block(rule: &Rule) -> bool {
+    match rule.selectors.as_slice() {
+        [selector] => matches!(
+            selector.parts.as_slice(),
+            [SelectorPart::Pseudo(name, arguments)] if name == "export" && arguments.is_empty()
+        ),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub name: String,
+    pub value: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassName {
+    pub name: String,
+    pub scope: Scope,
+    pub span: Span, // First appearance
+}
+
+struct ClassCollector {
+    mode: Scope,
+    classes: Vec<ClassName>,
+    seen: HashSet<(String, Scope)>,
+    parents: Vec<Vec<Selector>>, // Resolved selectors of the enclosing rules
+}
+
+impl Visitor for ClassCollector {
+    fn visit_rule(&mut self, rule: &Rule) {
+        let parents = self.parents.last().map(Vec::as_slice).unwrap_or_default();
+        let selectors = if rule.selectors.is_empty() {
+            parents.to_vec()
+        } else {
+            rule.selectors
+                .iter()
+                .flat_map(|selector| selector.resolve(parents))
+                .collect::<Vec<_>>()
+        };
+        for selector in &selectors {
+            self.visit_selector(selector);
+        }
+        self.parents.push(selectors);
+        walk_nodes(self, &rule.children);
+        self.parents.pop();
+    }
 
-            if let TokenKind::Class(true) = token.kind {
-                // If the class is nested, we still consider it a class
-                classes.insert(token.value.to_string());
+    fn visit_selector(&mut self, selector: &Selector) {
+        for (name, span, scope) in selector.classes(self.mode) {
+            if self.seen.insert((name.to_string(), scope)) {
+                self.classes.push(ClassName {
+                    name: name.to_string(),
+                    scope,
+                    span,
+                });
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> ScssFile {
+        let path = format!(
+            "{}/tests/fixtures/{}.module.scss",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        ScssFile::new(Path::new(&path)).unwrap()
+    }
+
+    #[test]
+    fn test_unreadable_files_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.module.scss");
+        let error = ScssFile::new(&path).err().unwrap();
+        assert!(error.starts_with("Could not read"), "{error}");
+        fs::write(&path, [b'.', 0xff, b' ', b'{', b'}']).unwrap();
+        assert!(ScssFile::new(&path).is_err());
+    }
+
+    fn sorted_classes(scss_file: &ScssFile) -> Vec<String> {
+        scoped_classes(scss_file, Scope::Local, Scope::Local)
+    }
+
+    fn scoped_classes(scss_file: &ScssFile, mode: Scope, scope: Scope) -> Vec<String> {
+        let mut classes = scss_file
+            .classes(mode)
+            .into_iter()
+            .filter(|class| class.scope == scope)
+            .map(|class| class.name)
+            .collect::<Vec<_>>();
+        classes.sort();
         classes
     }
+
+    #[test]
+    fn test_button_fixture() {
+        let scss_file = fixture("button");
+        assert!(scss_file.diagnostics.is_empty());
+        assert_eq!(
+            sorted_classes(&scss_file),
+            vec!["button", "button-group", "icon", "label", "primary", "secondary"]
+        );
+    }
+
+    #[test]
+    fn test_layout_fixture() {
+        let scss_file = fixture("layout");
+        assert!(scss_file.diagnostics.is_empty());
+        assert_eq!(
+            sorted_classes(&scss_file),
+            vec!["container", "content", "fallback", "mobile-nav", "sidebar"]
+        );
+    }
+
+    #[test]
+    fn test_form_fixture() {
+        let scss_file = fixture("form");
+        assert!(scss_file.diagnostics.is_empty());
+        assert_eq!(
+            sorted_classes(&scss_file),
+            vec!["checkbox-label", "error-message", "field", "form", "submit"]
+        );
+    }
+
+    #[test]
+    fn test_card_fixture() {
+        let scss_file = fixture("card");
+        assert!(scss_file.diagnostics.is_empty());
+        assert_eq!(
+            sorted_classes(&scss_file),
+            vec![
+                "badge-error",
+                "badge-error-icon",
+                "badge-warning",
+                "badge-warning-icon",
+                "card",
+                "card--highlighted",
+                "card-list",
+                "card__body",
+                "card__body--compact",
+                "card__title",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_classes_outside_selectors_are_ignored() {
+        let scss_file = ScssFile::parse(
+            "@extend .placeholder;\n.a { @include button(.5rem); }\n",
+            "a.scss",
+        );
+        assert_eq!(sorted_classes(&scss_file), vec!["a"]);
+    }
+
+    #[test]
+    fn test_bem_suffixes() {
+        let scss_file = ScssFile::parse(
+            ".block {\n  &__element {\n    &--modifier {}\n  }\n  &--active, &-wide {}\n}\n",
+            "a.scss",
+        );
+        assert_eq!(
+            sorted_classes(&scss_file),
+            vec![
+                "block",
+                "block--active",
+                "block-wide",
+                "block__element",
+                "block__element--modifier"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bem_suffixes_with_selector_lists() {
+        let scss_file = ScssFile::parse(
+            ".a, .b .c {\n  &__x, &.y {\n    &--z {}\n  }\n}\n",
+            "a.scss",
+        );
+        assert_eq!(
+            sorted_classes(&scss_file),
+            vec!["a", "a__x", "a__x--z", "b", "c", "c__x", "c__x--z", "y", "y--z"]
+        );
+    }
+
+    #[test]
+    fn test_bem_suffix_without_parent_class() {
+        let scss_file = ScssFile::parse(
+            "div {\n  &__x {}\n}\n&__y {}\n.a:hover {\n  &-b {}\n}\n",
+            "a.scss",
+        );
+        assert_eq!(sorted_classes(&scss_file), vec!["a"]);
+    }
+
+    #[test]
+    fn test_global_and_local_functions() {
+        let scss_file = ScssFile::parse(
+            ":global(.app) .page {}\n.nav :global(.active) {}\n:local(.menu) {}\n",
+            "a.scss",
+        );
+        assert_eq!(
+            scoped_classes(&scss_file, Scope::Local, Scope::Local),
+            vec!["menu", "nav", "page"]
+        );
+        assert_eq!(
+            scoped_classes(&scss_file, Scope::Local, Scope::Global),
+            vec!["active", "app"]
+        );
+    }
+
+    #[test]
+    fn test_global_and_local_blocks() {
+        let scss_file = ScssFile::parse(
+            ":global {\n  .reset {\n    .inner {}\n  }\n}\n.card {\n  :global .theme-dark & {}\n  :global {\n    .tooltip {\n      :local(.arrow) {}\n    }\n  }\n}\n:global .a :local .b {}\n",
+            "a.scss",
+        );
+        assert_eq!(
+            scoped_classes(&scss_file, Scope::Local, Scope::Local),
+            vec!["arrow", "b", "card"]
+        );
+        assert_eq!(
+            scoped_classes(&scss_file, Scope::Local, Scope::Global),
+            vec!["a", "card", "inner", "reset", "theme-dark", "tooltip"]
+        );
+    }
+
+    #[test]
+    fn test_global_mode() {
+        let scss_file = ScssFile::parse(".a {}\n:local(.b) {}\n:local {\n  .c {}\n}\n", "a.scss");
+        assert_eq!(
+            scoped_classes(&scss_file, Scope::Global, Scope::Local),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            scoped_classes(&scss_file, Scope::Global, Scope::Global),
+            vec!["a"]
+        );
+    }
+
+    #[test]
+    fn test_classes_in_source_order() {
+        let scss_file = ScssFile::parse(
+            ".zebra {}\n.apple .mango {\n  &__x {}\n}\n.zebra:hover {}\n.banana {}\n",
+            "a.scss",
+        );
+        let classes = scss_file
+            .classes(Scope::Local)
+            .into_iter()
+            .map(|class| (class.name, class.span.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            vec![
+                ("zebra".to_string(), 1),
+                ("apple".to_string(), 2),
+                ("mango".to_string(), 2),
+                ("mango__x".to_string(), 3),
+                ("banana".to_string(), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_exports() {
+        let scss_file = ScssFile::parse(
+            "$primary: #0055ff;\n:export {\n  primaryColor: $primary;\n  spacing: 4px 8px\n}\n.a {\n  :export { ignored: 1px; }\n}\n",
+            "a.scss",
+        );
+        let exports = scss_file
+            .exports()
+            .into_iter()
+            .map(|export| (export.name, export.value, export.span.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            exports,
+            vec![
+                ("primaryColor".to_string(), "$primary".to_string(), 3),
+                ("spacing".to_string(), "4px 8px".to_string(), 4),
+            ]
+        );
+        assert_eq!(sorted_classes(&scss_file), vec!["a"]);
+    }
 }
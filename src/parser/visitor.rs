@@ -0,0 +1,45 @@
+use crate::parser::ast::{AtRule, Declaration, Node, Rule, Selector, Stylesheet};
+
+/// Walks a stylesheet. Every method defaults to visiting the children, so
+/// implementors only override the nodes they care about and call the matching
+/// `walk_*` function to keep descending.
+pub trait Visitor {
+    fn visit_stylesheet(&mut self, stylesheet: &Stylesheet) {
+        walk_nodes(self, &stylesheet.children);
+    }
+
+    fn visit_rule(&mut self, rule: &Rule) {
+        walk_rule(self, rule);
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &AtRule) {
+        walk_at_rule(self, at_rule);
+    }
+
+    fn visit_selector(&mut self, _selector: &Selector) {}
+
+    fn visit_declaration(&mut self, _declaration: &Declaration) {}
+}
+
+pub fn walk_nodes<V: Visitor + ?Sized>(visitor: &mut V, nodes: &[Node]) {
+    for node in nodes {
+        match node {
+            Node::Rule(rule) => visitor.visit_rule(rule),
+            Node::AtRule(at_rule) => visitor.visit_at_rule(at_rule),
+            Node::Declaration(declaration) => visitor.visit_declaration(declaration),
+        }
+    }
+}
+
+pub fn walk_rule<V: Visitor + ?Sized>(visitor: &mut V, rule: &Rule) {
+    for selector in &rule.selectors {
+        visitor.visit_selector(selector);
+    }
+    walk_nodes(visitor, &rule.children);
+}
+
+pub fn walk_at_rule<V: Visitor + ?Sized>(visitor: &mut V, at_rule: &AtRule) {
+    if let Some(children) = &at_rule.children {
+        walk_nodes(visitor, children);
+    }
+}
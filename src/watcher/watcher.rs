@@ -0,0 +1,364 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+use log::{info, warn};
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+use walkdir::WalkDir;
+
+use crate::generator::generator::{is_generated, Generator, Summary};
+use crate::loader::loader::{
+    get_scss_files_in, is_excluded, is_filtered_out, is_ignore_file, is_scss_file, Filter,
+};
+use crate::parser::scss::ScssFile;
+
+/// Starts watching the roots recursively. Events of a save burst arrive as
+/// one batch once nothing changed for `debounce`. Events stop when the
+/// returned debouncer is dropped.
+pub fn start(
+    roots: &[PathBuf],
+    debounce: Duration,
+) -> Result<(Debouncer<RecommendedWatcher>, Receiver<DebounceEventResult>), String> {
+    let (sender, receiver) = channel();
+    let mut debouncer = match new_debouncer(debounce, sender) {
+        Ok(debouncer) => debouncer,
+        Err(e) => return Err(format!("Could not start watching: {}", e)),
+    };
+    for root in roots {
+        if let Err(e) = debouncer.watcher().watch(root, RecursiveMode::Recursive) {
+            return Err(format!("Could not watch {}: {}", root.display(), e));
+        }
+    }
+    Ok((debouncer, receiver))
+}
+
+/// Regenerates the declarations of the stylesheets changed since the initial
+/// scan until the process is stopped.
+pub fn watch(
+    generator: &Generator,
+    roots: &[PathBuf],
+    filter: &Filter,
+    debounce: Duration,
+) -> Result<(), String> {
+    let (_debouncer, receiver) = start(roots, debounce)?;
+    for root in roots {
+        info!("Watching {} for changes...", root.display());
+    }
+    for result in receiver {
+        match result {
+            Ok(events) => {
+                let paths = events
+                    .into_iter()
+                    .map(|event| event.path)
+                    .collect::<Vec<_>>();
+                let summary = handle_changes(generator, roots, filter, &paths);
+                for (declaration_path, outcome) in &summary.changes {
+                    info!("{:?} {}", outcome, declaration_path.display());
+                }
+            }
+            Err(e) => warn!("Error while watching: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Brings the declarations of the changed paths up to date. Stylesheets that
+/// still exist are regenerated and the declarations of deleted ones are
+/// removed. A directory that was created or moved in is scanned as a whole,
+/// one that was deleted or moved out loses the declarations in `--out-dir`.
+/// Paths outside of `filter`, with its patterns relative to the root they are
+/// in, are ignored. Changed ignore files are read again.
+pub fn handle_changes(
+    generator: &Generator,
+    roots: &[PathBuf],
+    filter: &Filter,
+    paths: &[PathBuf],
+) -> Summary {
+    if paths.iter().any(|path| is_ignore_file(path)) {
+        filter.reload_ignore_files();
+    }
+    let mut stylesheets = BTreeSet::new();
+    let mut orphans = BTreeSet::new();
+    for path in paths {
+        let Some(root) = roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+        else {
+            continue;
+        };
+        if path.is_dir() && !is_excluded(root, path) && !is_filtered_out(root, path, filter) {
+            stylesheets.extend(get_scss_files_in(root, path, filter).map(|e| e.into_path()));
+        } else if is_scss_file(root, path, filter) {
+            stylesheets.insert(path.clone());
+        } else if !path.exists() {
+            orphans.extend(orphaned_declarations(generator, root, path, filter));
+        }
+    }
+
+    let mut summary = Summary::default();
+    for path in stylesheets {
+        let declaration_path = generator.declaration_path_for(&path);
+        // The file can be gone again by the time it is read
+        let result = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let scss_file = ScssFile::parse(&content, &path.display().to_string());
+                for diagnostic in &scss_file.diagnostics {
+                    warn!("{}", diagnostic);
+                }
+                generator.generate_declaration(&scss_file)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if !declaration_path.exists() {
+                    continue;
+                }
+                generator.remove_declaration(&declaration_path, false)
+            }
+            Err(e) => Err(format!("Could not read {}: {}", path.display(), e)),
+        };
+        if let Err(e) = &result {
+            eprintln!("Error updating declaration for {}: {}", path.display(), e);
+        }
+        summary.add(declaration_path, &result);
+    }
+    for declaration_path in orphans {
+        let result = generator.remove_declaration(&declaration_path, false);
+        if let Err(e) = &result {
+            eprintln!("{}", e);
+        }
+        summary.add(declaration_path, &result);
+    }
+    summary
+}
+
+/// Generated declarations of the stylesheets that were in `directory`. The
+/// declarations next to the stylesheets went away with them, this only finds
+/// the ones in `--out-dir`.
+fn orphaned_declarations(
+    generator: &Generator,
+    root: &Path,
+    directory: &Path,
+    filter: &Filter,
+) -> Vec<PathBuf> {
+    WalkDir::new(generator.declaration_dir(directory))
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|e| e.into_path())
+        .filter(|path| {
+            let source_path = generator.source_path(path);
+            path.to_string_lossy().ends_with(".d.ts")
+                && !source_path.exists()
+                && is_scss_file(root, &source_path, filter)
+                && is_generated(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::generator::{GeneratorOptions, Outcome};
+    use std::slice;
+    use std::time::Instant;
+
+    fn generator(root: &Path) -> Generator {
+        Generator::new(GeneratorOptions::default()).with_root(root)
+    }
+
+    #[test]
+    fn test_handle_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let generator = generator(&root);
+        let stylesheet = root.join("a.module.scss");
+        let excluded = root.join("node_modules/b.module.scss");
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(&stylesheet, ".button {}\n").unwrap();
+        fs::write(&excluded, ".button {}\n").unwrap();
+
+        let summary = handle_changes(
+            &generator,
+            slice::from_ref(&root),
+            &Filter::default(),
+            &[stylesheet.clone(), excluded.clone()],
+        );
+        assert_eq!(
+            summary.changes,
+            vec![(root.join("a.module.scss.d.ts"), Outcome::Created)]
+        );
+        assert!(!root.join("node_modules/b.module.scss.d.ts").exists());
+
+        // Declarations written by the generator don't trigger anything
+        let summary = handle_changes(
+            &generator,
+            slice::from_ref(&root),
+            &Filter::default(),
+            &[root.join("a.module.scss.d.ts")],
+        );
+        assert_eq!(summary, Summary::default());
+
+        fs::remove_file(&stylesheet).unwrap();
+        let summary = handle_changes(
+            &generator,
+            slice::from_ref(&root),
+            &Filter::default(),
+            &[stylesheet],
+        );
+        assert_eq!(
+            summary.changes,
+            vec![(root.join("a.module.scss.d.ts"), Outcome::Removed)]
+        );
+        assert!(!root.join("a.module.scss.d.ts").exists());
+    }
+
+    #[test]
+    fn test_handle_changes_scans_new_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("components/card")).unwrap();
+        fs::write(root.join("components/card/card.module.scss"), ".card {}\n").unwrap();
+        fs::write(root.join("components/button.module.scss"), ".button {}\n").unwrap();
+
+        let summary = handle_changes(
+            &generator(&root),
+            slice::from_ref(&root),
+            &Filter::default(),
+            &[root.join("components")],
+        );
+        assert_eq!(summary.created, 2);
+        assert!(root.join("components/card/card.module.scss.d.ts").exists());
+    }
+
+    #[test]
+    fn test_handle_changes_applies_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/legacy")).unwrap();
+        fs::write(root.join("src/a.module.scss"), ".a {}\n").unwrap();
+        fs::write(root.join("src/legacy/b.module.scss"), ".b {}\n").unwrap();
+        let filter = Filter::new(&[], &["src/legacy".to_string()]).unwrap();
+
+        let summary = handle_changes(
+            &generator(&root),
+            slice::from_ref(&root),
+            &filter,
+            &[
+                root.join("src/legacy/b.module.scss"),
+                root.join("src/legacy"),
+            ],
+        );
+        assert_eq!(summary, Summary::default());
+        let summary = handle_changes(
+            &generator(&root),
+            slice::from_ref(&root),
+            &filter,
+            &[root.join("src")],
+        );
+        assert_eq!(
+            summary.changes,
+            vec![(root.join("src/a.module.scss.d.ts"), Outcome::Created)]
+        );
+    }
+
+    #[test]
+    fn test_handle_changes_removes_declarations_of_moved_out_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let root = base.join("src");
+        let out_dir = base.join("types");
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::write(root.join("c/a.module.scss"), ".a {}\n").unwrap();
+        fs::write(root.join("b.module.scss"), ".b {}\n").unwrap();
+        let generator = Generator::new(GeneratorOptions {
+            out_dir: Some(out_dir.clone()),
+            ..GeneratorOptions::default()
+        })
+        .with_root(&root);
+        let summary = handle_changes(
+            &generator,
+            slice::from_ref(&root),
+            &Filter::default(),
+            slice::from_ref(&root),
+        );
+        assert_eq!(summary.created, 2);
+
+        fs::rename(root.join("c"), base.join("c")).unwrap();
+        let summary = handle_changes(
+            &generator,
+            slice::from_ref(&root),
+            &Filter::default(),
+            &[root.join("c")],
+        );
+        assert_eq!(
+            summary.changes,
+            vec![(out_dir.join("c/a.module.scss.d.ts"), Outcome::Removed)]
+        );
+        assert!(out_dir.join("b.module.scss.d.ts").exists());
+    }
+
+    #[test]
+    fn test_handle_changes_in_several_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let roots = vec![base.join("a"), base.join("b")];
+        for root in &roots {
+            fs::create_dir_all(root).unwrap();
+            fs::write(root.join("card.module.scss"), ".card {}\n").unwrap();
+        }
+        fs::write(base.join("outside.module.scss"), ".card {}\n").unwrap();
+        // Patterns are relative to the root of each path
+        let filter = Filter::new(&["card.module.scss".to_string()], &[]).unwrap();
+
+        let summary = handle_changes(
+            &generator(&base),
+            &roots,
+            &filter,
+            &[
+                base.join("a/card.module.scss"),
+                base.join("b/card.module.scss"),
+                base.join("outside.module.scss"),
+            ],
+        );
+        assert_eq!(summary.created, 2);
+        assert!(!base.join("outside.module.scss.d.ts").exists());
+    }
+
+    #[test]
+    fn test_watch_regenerates_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let generator = generator(&root);
+        let declaration_path = root.join("a.module.scss.d.ts");
+        let (_debouncer, receiver) =
+            start(slice::from_ref(&root), Duration::from_millis(50)).unwrap();
+
+        fs::write(root.join("a.module.scss"), ".button {}\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !declaration_path.exists() && Instant::now() < deadline {
+            if let Ok(Ok(events)) = receiver.recv_timeout(Duration::from_millis(100)) {
+                let paths = events
+                    .into_iter()
+                    .map(|event| event.path)
+                    .collect::<Vec<_>>();
+                handle_changes(
+                    &generator,
+                    slice::from_ref(&root),
+                    &Filter::default(),
+                    &paths,
+                );
+            }
+        }
+        assert!(fs::read_to_string(&declaration_path)
+            .unwrap()
+            .contains("button: string;"));
+    }
+}